@@ -0,0 +1,167 @@
+use crate::*;
+
+/// Max accounts per add/remove call, so a batch always fits in the gas limit
+pub const MAX_LIST_BATCH: usize = 100;
+
+// Denylist: sanctioned or known exploit accounts. They can not receive claims nor claim.
+// Allowlist: an airdrop with allowlist_required only pays accounts that passed an off-chain check
+#[near_bindgen]
+impl GradualReleaseContract {
+    // the attached NEAR pays the storage of the new entries, the excess is refunded
+    #[payable]
+    pub fn add_to_denylist(&mut self, accounts: Vec<AccountId>) {
+        self.assert_role(Role::AirdropAdmin);
+        self.assert_not_paused();
+        assert_list_batch(&accounts);
+        let storage_payer_id = env::predecessor_account_id();
+        let storage_before = env::storage_usage();
+        let mut added = vec![];
+        for account_id in accounts.iter() {
+            // an existing entry keeps its payer
+            if self.denylist.get(account_id).is_none() {
+                self.denylist.insert(account_id, &storage_payer_id);
+                added.push(events::AccountData {
+                    account_id: account_id.clone(),
+                });
+            }
+        }
+        events::Event::emit_chunks(&added, events::Event::DenylistAdded);
+        log!(
+            "{} added {} accounts to the denylist",
+            env::predecessor_account_id(),
+            accounts.len()
+        );
+        self.internal_pay_storage(storage_before, env::attached_deposit(), &storage_payer_id);
+    }
+
+    // the freed storage goes back to the payer of each entry
+    pub fn remove_from_denylist(&mut self, accounts: Vec<AccountId>) {
+        self.assert_role(Role::AirdropAdmin);
+        self.assert_not_paused();
+        assert_list_batch(&accounts);
+        let mut freed = FreedStorage::new();
+        let mut removed = vec![];
+        for account_id in accounts.iter() {
+            let storage_before = env::storage_usage();
+            if let Some(storage_payer_id) = self.denylist.remove(account_id) {
+                *freed.entry(Some(storage_payer_id)).or_insert(0) +=
+                    storage_before.saturating_sub(env::storage_usage());
+                removed.push(events::AccountData {
+                    account_id: account_id.clone(),
+                });
+            }
+        }
+        self.internal_refund_storage(freed);
+        events::Event::emit_chunks(&removed, events::Event::DenylistRemoved);
+        log!(
+            "{} removed {} accounts from the denylist",
+            env::predecessor_account_id(),
+            accounts.len()
+        );
+    }
+
+    // it can only be turned on before launch, so the claims of a live airdrop are never blocked
+    pub fn set_allowlist_required(&mut self, airdrop_index: u16, allowlist_required: bool) {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        assert!(
+            !allowlist_required || !airdrop.is_launched(),
+            "Airdrop {} was already launched, the allowlist can only be required before launch",
+            airdrop_index
+        );
+        airdrop.allowlist_required = allowlist_required;
+    }
+
+    // the attached NEAR pays the storage of the new entries, the excess is refunded
+    #[payable]
+    pub fn add_to_allowlist(&mut self, airdrop_index: u16, accounts: Vec<AccountId>) {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        assert_list_batch(&accounts);
+        let storage_payer_id = env::predecessor_account_id();
+        let storage_before = env::storage_usage();
+        let mut added = vec![];
+        for account_id in accounts {
+            let key = (airdrop_index, account_id);
+            // an existing entry keeps its payer
+            if !self.allowlist.contains_key(&key) {
+                self.allowlist.insert(&key, &storage_payer_id);
+                added.push(events::AllowlistData {
+                    airdrop_index,
+                    account_id: key.1,
+                });
+            }
+        }
+        events::Event::emit_chunks(&added, events::Event::AllowlistAdded);
+        self.internal_pay_storage(storage_before, env::attached_deposit(), &storage_payer_id);
+    }
+
+    // the freed storage goes back to the payer of each entry
+    pub fn remove_from_allowlist(&mut self, airdrop_index: u16, accounts: Vec<AccountId>) {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        assert_list_batch(&accounts);
+        let mut freed = FreedStorage::new();
+        let mut removed = vec![];
+        for account_id in accounts {
+            let storage_before = env::storage_usage();
+            let key = (airdrop_index, account_id);
+            if let Some(storage_payer_id) = self.allowlist.remove(&key) {
+                *freed.entry(Some(storage_payer_id)).or_insert(0) +=
+                    storage_before.saturating_sub(env::storage_usage());
+                removed.push(events::AllowlistData {
+                    airdrop_index,
+                    account_id: key.1,
+                });
+            }
+        }
+        events::Event::emit_chunks(&removed, events::Event::AllowlistRemoved);
+        self.internal_refund_storage(freed);
+    }
+
+    pub fn get_denylist(&self, from_index: u32, limit: u32) -> Vec<AccountId> {
+        let accounts = self.denylist.keys_as_vector();
+        let start = from_index as u64;
+        (start..std::cmp::min(start + limit as u64, accounts.len()))
+            .map(|index| accounts.get(index).unwrap())
+            .collect()
+    }
+
+    pub fn is_denylisted(&self, account_id: AccountId) -> bool {
+        self.denylist.get(&account_id).is_some()
+    }
+
+    pub fn is_allowlisted(&self, airdrop_index: u16, account_id: AccountId) -> bool {
+        self.allowlist.contains_key(&(airdrop_index, account_id))
+    }
+}
+
+impl GradualReleaseContract {
+    pub(crate) fn assert_not_denylisted(&self, account_id: &AccountId) {
+        require!(
+            self.denylist.get(account_id).is_none(),
+            format!("{} is denylisted", account_id)
+        );
+    }
+
+    pub(crate) fn assert_can_claim(&self, airdrop_index: u16, account_id: &AccountId) {
+        self.assert_not_denylisted(account_id);
+        require!(
+            !self.airdrops[airdrop_index as u32].live().allowlist_required
+                || self.allowlist.contains_key(&(airdrop_index, account_id.clone())),
+            format!(
+                "{} is not in the allowlist of airdrop {}",
+                account_id, airdrop_index
+            )
+        );
+    }
+}
+
+fn assert_list_batch(accounts: &[AccountId]) {
+    assert!(
+        accounts.len() <= MAX_LIST_BATCH,
+        "At most {} accounts per call",
+        MAX_LIST_BATCH
+    );
+}
@@ -1,13 +1,64 @@
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::{U128, U64},
+    serde::{Deserialize, Serialize},
     AccountId,
 };
 
-pub type StatusCode = u8;
-pub mod status_code {
-    pub const DISABLED: u8 = 0;
-    pub const ENABLED: u8 = 1;
-    pub const ARCHIVED: u8 = 2;
+use crate::utils::{get_current_epoch_millis, proportional};
+
+/// Airdrop lifecycle
+///
+/// Draft -> Funded -> Active <-> Paused -> Ended -> Closed
+///
+/// - Draft: registered, claims can be added, changed and removed
+/// - Funded: the contract balance covers the claims (checked by confirm_funding), ready to enable
+/// - Active: users can claim
+/// - Paused: claims are temporarily blocked, can be re-enabled
+/// - Ended: archived, no longer appears in get_airdrops()
+/// - Closed: final state. A Draft or Funded airdrop is closed when cancelled
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AirdropStatus {
+    Draft,
+    Funded,
+    Active,
+    Paused,
+    Ended,
+    Closed,
+}
+
+impl AirdropStatus {
+    pub fn can_change_to(&self, new_status: AirdropStatus) -> bool {
+        use AirdropStatus::*;
+        matches!(
+            (self, new_status),
+            (Draft, Funded)
+                | (Draft, Closed)
+                | (Funded, Active)
+                | (Funded, Closed)
+                | (Active, Paused)
+                | (Active, Ended)
+                | (Paused, Active)
+                | (Paused, Ended)
+                | (Ended, Closed)
+        )
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StatusChange {
+    pub status: AirdropStatus,
+    pub timestamp_ms: u64,
+}
+
+impl StatusChange {
+    pub fn now(status: AirdropStatus) -> Self {
+        Self {
+            status,
+            timestamp_ms: get_current_epoch_millis(),
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -16,29 +67,210 @@ pub struct TimestampPeriod {
     pub end_ms: u64,
 }
 
+impl TimestampPeriod {
+    // part of amount released at at_ms, on the linear release curve
+    pub fn linear_unlocked(&self, amount: u128, at_ms: u64) -> u128 {
+        if at_ms < self.start_ms {
+            0
+        } else if at_ms >= self.end_ms {
+            amount
+        } else {
+            proportional(
+                amount,
+                (at_ms - self.start_ms).into(),
+                (self.end_ms - self.start_ms).into(),
+            )
+        }
+    }
+
+    // true if new_period releases at least as much as self at from_ms and at every later point.
+    // Both curves are linear between their start and end, so checking every breakpoint
+    // (and the instant before it, for zero-length periods) is enough
+    pub fn is_never_ahead_of(
+        &self,
+        new_period: &TimestampPeriod,
+        amount: u128,
+        from_ms: u64,
+    ) -> bool {
+        [
+            from_ms,
+            self.start_ms,
+            self.end_ms,
+            new_period.start_ms,
+            new_period.end_ms,
+        ]
+        .iter()
+        .flat_map(|&ms| [ms.saturating_sub(1), ms])
+        .filter(|&ms| ms >= from_ms)
+        .all(|ms| new_period.linear_unlocked(amount, ms) >= self.linear_unlocked(amount, ms))
+    }
+}
+
+// arguments of register_airdrop_step_2
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AirdropRegistration {
+    pub title: String,
+    pub token_contract: AccountId,
+    pub start_timestamp_ms: U64,
+    pub end_timestamp_ms: U64,
+    pub manager_id: AccountId,
+    pub sponsored: bool,
+    // the caller, pays the airdrop storage with the attached deposit
+    pub storage_payer_id: AccountId,
+    pub storage_deposit: U128,
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Airdrop {
-    pub status_code: StatusCode,
+    pub status: AirdropStatus,
     pub title: String,
     pub token_contract: AccountId,
+    // can add claims, enable, disable, archive and change the schedule of this airdrop
+    pub manager_id: AccountId,
+    // sponsored airdrops are created by anyone and funded with ft_transfer_call
+    pub sponsor_id: Option<AccountId>,
+    // tokens deposited by the sponsor (after platform fee), not yet returned
+    pub deposited_tokens: u128,
     pub token_symbol: String,
     pub token_decimals: u8,
     pub release_schedule: TimestampPeriod,
+    // accrual freeze: while frozen the vesting clock stops, and the release moves back by the frozen time
+    pub frozen_since_ms: Option<u64>,
+    pub total_frozen_ms: u64,
+    // only accounts in the allowlist of this airdrop can claim
+    pub allowlist_required: bool,
+    pub total_distributed: u128,
+    pub total_claimed: u128,
+    // unclaimed tokens of the claims removed by purge_airdrop, no longer owed to the users
+    pub total_reclaimed: u128,
+    // sponsor refund sent and not resolved yet, the airdrop is not purged meanwhile
+    pub pending_sponsor_refund: u128,
+    // refund of cancel_airdrop sent and not resolved yet, or failed. Cleared when the transfer succeeds
+    pub cancel_refund: Option<CancelRefund>,
+    // user claim rows of this airdrop, it can only be purged when none are left
+    pub claim_count: u32,
+    // every status change, starting with Draft at registration
+    pub status_history: Vec<StatusChange>,
+    // set by cancel_airdrop, its claims are no longer in total_in_claims_per_token
+    pub cancelled: bool,
+    // NEAR paid for the storage of this airdrop, returned to storage_payer_id when it is purged.
+    // The storage of its claims and allowlist entries is paid by each row, see internal_pay_storage
+    pub storage_payer_id: AccountId,
+    pub storage_deposit: u128,
+}
+
+// refund of a cancelled airdrop that was not sponsored
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CancelRefund {
+    pub refund_to: AccountId,
+    pub amount: u128,
+    // the transfer failed, retry_cancel_refund sends it again
+    pub failed: bool,
+}
+
+// a purged airdrop only keeps its final totals, see purge.rs
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PurgedAirdrop {
+    pub token_contract: AccountId,
     pub total_distributed: u128,
     pub total_claimed: u128,
+    pub purged_at_ms: u64,
+}
+
+// entry of the airdrops Vector, a purged airdrop keeps its index so the later ones keep theirs
+// almost every entry is Live, boxing it would only add an allocation
+#[allow(clippy::large_enum_variant)]
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum AirdropEntry {
+    Live(Airdrop),
+    Purged(PurgedAirdrop),
+}
+
+impl AirdropEntry {
+    pub fn live(&self) -> &Airdrop {
+        match self {
+            AirdropEntry::Live(airdrop) => airdrop,
+            AirdropEntry::Purged(_) => panic!("The airdrop was purged"),
+        }
+    }
+
+    pub fn live_mut(&mut self) -> &mut Airdrop {
+        match self {
+            AirdropEntry::Live(airdrop) => airdrop,
+            AirdropEntry::Purged(_) => panic!("The airdrop was purged"),
+        }
+    }
 }
 
 impl Airdrop {
     pub fn is_enabled(&self) -> bool {
-        self.status_code == status_code::ENABLED
+        self.status == AirdropStatus::Active
+    }
+
+    // users may already have unlocked tokens
+    pub fn is_launched(&self) -> bool {
+        !matches!(self.status, AirdropStatus::Draft | AirdropStatus::Funded)
+    }
+
+    // sponsor tokens not assigned to users, or reclaimed by the purge,
+    // returned to the sponsor when the airdrop is closed or purged
+    pub fn unallocated_tokens(&self) -> u128 {
+        self.deposited_tokens + self.total_reclaimed - self.total_distributed
+    }
+
+    // frozen time counted since the release start, including a freeze in progress
+    pub fn frozen_ms(&self, now_ms: u64) -> u64 {
+        self.total_frozen_ms
+            + self.frozen_since_ms.map_or(0, |since_ms| {
+                now_ms.saturating_sub(since_ms.max(self.release_schedule.start_ms))
+            })
+    }
+
+    // the release schedule moved back by the frozen time, used to compute unlocked amounts
+    pub fn effective_schedule(&self) -> TimestampPeriod {
+        let frozen_ms = self.frozen_ms(get_current_epoch_millis());
+        TimestampPeriod {
+            start_ms: self.release_schedule.start_ms + frozen_ms,
+            end_ms: self.release_schedule.end_ms + frozen_ms,
+        }
+    }
+
+    pub fn freeze_accrual(&mut self) {
+        self.frozen_since_ms = Some(get_current_epoch_millis());
+    }
+
+    pub fn resume_accrual(&mut self) {
+        self.total_frozen_ms = self.frozen_ms(get_current_epoch_millis());
+        self.frozen_since_ms = None;
+    }
+
+    // closed before launch with cancel_airdrop. Its claims are no longer in total_in_claims_per_token
+    pub fn was_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    // closed, and no user can still expect tokens from it:
+    // cancelled before launch, fully claimed, or past its release end
+    pub fn can_purge(&self, now_ms: u64) -> bool {
+        self.status == AirdropStatus::Closed
+            && (self.was_cancelled()
+                || self.total_claimed >= self.total_distributed
+                || self.effective_schedule().end_ms <= now_ms)
     }
 
-    pub fn change_status(&mut self, new_status: StatusCode) {
+    pub fn change_status(&mut self, new_status: AirdropStatus) {
         assert!(
-            self.status_code != new_status,
-            "ERR: Airdrop status is already {}",
+            self.status.can_change_to(new_status),
+            "ERR: Airdrop status can not change from {:?} to {:?}",
+            self.status,
             new_status
         );
-        self.status_code = new_status;
+        // a freeze only lasts while the airdrop is paused
+        if self.status == AirdropStatus::Paused {
+            self.resume_accrual();
+        }
+        self.status = new_status;
+        self.status_history.push(StatusChange::now(new_status));
     }
 }
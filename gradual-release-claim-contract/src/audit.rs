@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::view::{AuditAirdropSumJSON, AuditMismatchJSON, AuditPageJSON, AuditTokenJSON};
+use crate::*;
+
+// Recomputes the totals kept by the contract from the user claims, a page of users per call.
+// A view keeps nothing between calls, so the caller passes back the sums of the previous page.
+// Run it while no claims change, e.g. with the contract paused after an upgrade.
+#[near_bindgen]
+impl GradualReleaseContract {
+    // adds the claims of up to `limit` users from `from_index` to `sums` (None on the first page).
+    // Call it again with the returned next_index and sums until `done`,
+    // the final page compares the sums with total_in_claims_per_token and the airdrop totals
+    pub fn audit(
+        &self,
+        from_index: u32,
+        limit: u32,
+        sums: Option<Vec<AuditAirdropSumJSON>>,
+    ) -> AuditPageJSON {
+        let mut sums: BTreeMap<u16, AuditAirdropSumJSON> = sums
+            .unwrap_or_default()
+            .into_iter()
+            .map(|sum| (sum.airdrop_index, sum))
+            .collect();
+
+        // migrated users first, then the ones still in the legacy layout, as get_users
+        let keys = self.user_airdrops.keys_as_vector();
+        let legacy_keys = self.legacy_claims.keys_as_vector();
+        let user_count = keys.len() + legacy_keys.len();
+        let end = std::cmp::min(from_index as u64 + limit as u64, user_count);
+        for index in from_index as u64..end {
+            let migrated = index < keys.len();
+            let account_id = if migrated {
+                keys.get(index).unwrap()
+            } else {
+                legacy_keys.get(index - keys.len()).unwrap()
+            };
+            for claim in self.internal_get_user_claims(&account_id) {
+                let sum = sums
+                    .entry(claim.airdrop_index)
+                    .or_insert(AuditAirdropSumJSON {
+                        airdrop_index: claim.airdrop_index,
+                        assigned_tokens: U128(0),
+                        claimed_tokens: U128(0),
+                        claim_count: 0,
+                    });
+                sum.assigned_tokens.0 += claim.assigned_tokens;
+                sum.claimed_tokens.0 += claim.claimed_tokens;
+                if migrated {
+                    sum.claim_count += 1;
+                }
+            }
+        }
+
+        let done = end >= user_count;
+        let (tokens, mismatches) = if done {
+            self.internal_audit_totals(&sums)
+        } else {
+            (vec![], vec![])
+        };
+        AuditPageJSON {
+            next_index: std::cmp::max(end, from_index as u64) as u32,
+            user_count,
+            done,
+            sums: sums.into_values().collect(),
+            tokens,
+            mismatches,
+        }
+    }
+}
+
+impl GradualReleaseContract {
+    fn internal_audit_totals(
+        &self,
+        sums: &BTreeMap<u16, AuditAirdropSumJSON>,
+    ) -> (Vec<AuditTokenJSON>, Vec<AuditMismatchJSON>) {
+        let mut mismatches = Vec::new();
+        let mut recomputed_per_token = BTreeMap::<AccountId, u128>::new();
+        for (index, entry) in self.airdrops.iter().enumerate() {
+            let airdrop_index = index as u16;
+            let (assigned, claimed, claim_count) =
+                sums.get(&airdrop_index).map_or((0, 0, 0), |sum| {
+                    (sum.assigned_tokens.0, sum.claimed_tokens.0, sum.claim_count)
+                });
+            let mut mismatch = |error: String| {
+                mismatches.push(AuditMismatchJSON {
+                    token_contract: None,
+                    airdrop_index: Some(airdrop_index),
+                    error,
+                })
+            };
+            let airdrop = match entry {
+                airdrop::AirdropEntry::Live(airdrop) => airdrop,
+                airdrop::AirdropEntry::Purged(purged) => {
+                    if assigned > 0 || claim_count > 0 {
+                        mismatch(format!(
+                            "purged, but {} claims with {} assigned tokens found",
+                            claim_count, assigned
+                        ));
+                    }
+                    *recomputed_per_token
+                        .entry(purged.token_contract.clone())
+                        .or_default() += assigned.saturating_sub(claimed);
+                    continue;
+                }
+            };
+            if airdrop.total_claimed > airdrop.total_distributed {
+                mismatch(format!(
+                    "total_claimed {} is more than total_distributed {}",
+                    airdrop.total_claimed, airdrop.total_distributed
+                ));
+            }
+            if airdrop.claim_count != claim_count {
+                mismatch(format!(
+                    "claim_count is {}, {} claims found",
+                    airdrop.claim_count, claim_count
+                ));
+            }
+            // cancel_airdrop removes the claims from the totals, but keeps them
+            if airdrop.was_cancelled() {
+                continue;
+            }
+            if assigned + airdrop.total_claimed != airdrop.total_distributed + claimed {
+                mismatch(format!(
+                    "total_distributed - total_claimed is {}, the claims add up to {}",
+                    airdrop
+                        .total_distributed
+                        .saturating_sub(airdrop.total_claimed),
+                    assigned.saturating_sub(claimed)
+                ));
+            }
+            *recomputed_per_token
+                .entry(airdrop.token_contract.clone())
+                .or_default() += assigned.saturating_sub(claimed);
+        }
+
+        // every token with a recorded total, or with claims
+        let mut tokens = Vec::new();
+        for token_contract in self.total_in_claims_per_token.keys() {
+            recomputed_per_token.entry(token_contract).or_default();
+        }
+        for (token_contract, recomputed) in recomputed_per_token {
+            let total_in_claims = self
+                .total_in_claims_per_token
+                .get(&token_contract)
+                .unwrap_or(0);
+            if total_in_claims != recomputed {
+                mismatches.push(AuditMismatchJSON {
+                    token_contract: Some(token_contract.clone()),
+                    airdrop_index: None,
+                    error: format!(
+                        "total_in_claims_per_token is {}, the claims add up to {}",
+                        total_in_claims, recomputed
+                    ),
+                });
+            }
+            tokens.push(AuditTokenJSON {
+                token_contract,
+                total_in_claims: total_in_claims.into(),
+                recomputed: recomputed.into(),
+            });
+        }
+        (tokens, mismatches)
+    }
+}
@@ -0,0 +1,162 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+
+use crate::*;
+
+// User claims are stored one row per (account, airdrop), so a claim only reads and writes its own row.
+// user_airdrops is the per-user index: the airdrop indexes of each user's claims.
+// legacy_claims keeps the v2.0.0 layout, a Vec of claims per user. A user is moved to the
+// new layout on the first write to their claims, or in batches with migrate_claims.
+
+/// claim row of the v2.0.0 layout
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyUserClaimInfo {
+    pub airdrop_index: u16,
+    pub assigned_tokens: u128,
+    pub claimed_tokens: u128,
+}
+
+impl From<LegacyUserClaimInfo> for UserClaimInfo {
+    fn from(legacy: LegacyUserClaimInfo) -> Self {
+        UserClaimInfo {
+            airdrop_index: legacy.airdrop_index,
+            assigned_tokens: legacy.assigned_tokens,
+            claimed_tokens: legacy.claimed_tokens,
+            frozen_reason: None,
+            storage_payer_id: None,
+        }
+    }
+}
+
+#[near_bindgen]
+impl GradualReleaseContract {
+    // moves up to `limit` users to the new layout, returns the number of users left to migrate
+    pub fn migrate_claims(&mut self, limit: u32) -> u64 {
+        self.assert_role(Role::AirdropAdmin);
+        for _ in 0..limit {
+            let keys = self.legacy_claims.keys_as_vector();
+            if keys.is_empty() {
+                break;
+            }
+            // the last one, so no other key has to be moved
+            let account_id = keys.get(keys.len() - 1).unwrap();
+            self.internal_migrate_user(&account_id);
+        }
+        let remaining = self.legacy_claims.len();
+        log!("Claims migration: {} users left", remaining);
+        remaining
+    }
+}
+
+impl GradualReleaseContract {
+    // moves the claims of a user out of the legacy layout, no-op if already moved
+    pub(crate) fn internal_migrate_user(&mut self, account_id: &AccountId) {
+        if self.legacy_claims.is_empty() {
+            return;
+        }
+        if let Some(legacy_claims) = self.legacy_claims.remove(account_id) {
+            for claim in legacy_claims {
+                self.internal_save_claim(account_id, &claim.into());
+            }
+        }
+    }
+
+    // a claim in the new layout. Callers that write must call internal_migrate_user first
+    pub(crate) fn internal_get_claim(
+        &self,
+        account_id: &AccountId,
+        airdrop_index: u16,
+    ) -> Option<UserClaimInfo> {
+        self.claims.get(&(account_id.clone(), airdrop_index))
+    }
+    pub(crate) fn internal_get_claim_or_panic(
+        &self,
+        account_id: &AccountId,
+        airdrop_index: u16,
+    ) -> UserClaimInfo {
+        match self.internal_get_claim(account_id, airdrop_index) {
+            Some(claim) => claim,
+            None => panic!("{} has no claim for airdrop {}", account_id, airdrop_index),
+        }
+    }
+
+    // the airdrop indexes of a user's claims, in the new layout
+    pub(crate) fn internal_get_user_airdrops(&self, account_id: &AccountId) -> Vec<u16> {
+        self.user_airdrops.get(account_id).unwrap_or_default()
+    }
+
+    // all claims of a user, from either layout. For views
+    pub(crate) fn internal_get_user_claims(&self, account_id: &AccountId) -> Vec<UserClaimInfo> {
+        match self.user_airdrops.get(account_id) {
+            Some(airdrop_indexes) => airdrop_indexes
+                .into_iter()
+                .map(|airdrop_index| self.internal_get_claim_or_panic(account_id, airdrop_index))
+                .collect(),
+            None => self
+                .legacy_claims
+                .get(account_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(UserClaimInfo::from)
+                .collect(),
+        }
+    }
+
+    // inserts or overwrites a claim, a new one is added to the user index
+    pub(crate) fn internal_save_claim(&mut self, account_id: &AccountId, claim: &UserClaimInfo) {
+        let key = (account_id.clone(), claim.airdrop_index);
+        if self.claims.insert(&key, claim).is_none() {
+            self.airdrops[claim.airdrop_index as u32].live_mut().claim_count += 1;
+            let mut airdrop_indexes = self.internal_get_user_airdrops(account_id);
+            airdrop_indexes.push(claim.airdrop_index);
+            self.user_airdrops.insert(account_id, &airdrop_indexes);
+        }
+    }
+
+    // removes claims of a user, and the user from the index when it has no claims left
+    // adds the freed storage to `freed`, split between the payers of the removed claims
+    // returns the removed claims, for the claims_removed event
+    pub(crate) fn internal_remove_claims(
+        &mut self,
+        account_id: &AccountId,
+        removed: &[u16],
+        freed: &mut FreedStorage,
+    ) -> Vec<events::ClaimRemovedData> {
+        let storage_before = env::storage_usage();
+        let mut removed_claims = vec![];
+        let mut payers = vec![];
+        for airdrop_index in removed {
+            if let Some(claim) = self.claims.remove(&(account_id.clone(), *airdrop_index)) {
+                payers.push(claim.storage_payer_id.clone());
+                let airdrop = self.airdrops[*airdrop_index as u32].live_mut();
+                airdrop.claim_count -= 1;
+                // the claims of a cancelled airdrop were no longer owed since the cancel
+                let unclaimed_amount = if airdrop.was_cancelled() {
+                    0
+                } else {
+                    claim.assigned_tokens.saturating_sub(claim.claimed_tokens)
+                };
+                removed_claims.push(events::ClaimRemovedData {
+                    airdrop_index: *airdrop_index,
+                    account_id: account_id.clone(),
+                    unclaimed_amount: U128(unclaimed_amount),
+                });
+            }
+        }
+        let mut airdrop_indexes = self.internal_get_user_airdrops(account_id);
+        airdrop_indexes.retain(|airdrop_index| !removed.contains(airdrop_index));
+        if airdrop_indexes.is_empty() {
+            self.user_airdrops.remove(account_id);
+        } else {
+            self.user_airdrops.insert(account_id, &airdrop_indexes);
+        }
+        let freed_bytes = storage_before.saturating_sub(env::storage_usage());
+        for (n, payer_id) in payers.iter().enumerate() {
+            let mut share = freed_bytes / payers.len() as u64;
+            if n == 0 {
+                share += freed_bytes % payers.len() as u64;
+            }
+            *freed.entry(payer_id.clone()).or_insert(0) += share;
+        }
+        removed_claims
+    }
+}
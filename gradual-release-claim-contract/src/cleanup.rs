@@ -0,0 +1,97 @@
+use crate::view::CleanupProgressJSON;
+use crate::*;
+
+// Fully claimed claims are only kept for the views. Removing them frees their storage,
+// returned to the storage payer of each claim, or to storage_treasury_id when the contract paid it
+#[near_bindgen]
+impl GradualReleaseContract {
+    // removes the used claims of the listed accounts
+    pub fn remove_used_claims(&mut self, accounts: Vec<AccountId>) {
+        let mut freed = FreedStorage::new();
+        let mut removed = vec![];
+        for account_id in accounts {
+            self.internal_migrate_user(&account_id);
+            removed.extend(self.internal_remove_used_claims(&account_id, &mut freed));
+        }
+        self.internal_refund_storage(freed);
+        events::Event::emit_claims_removed(&removed);
+    }
+
+    // removes the used claims of up to `limit` users from `from_index`
+    // call it again with the returned next_index until it reaches user_count
+    pub fn cleanup_fully_claimed(&mut self, from_index: u32, limit: u32) -> CleanupProgressJSON {
+        require!(
+            self.legacy_claims.is_empty(),
+            "Claims not migrated yet, call migrate_claims first"
+        );
+        let mut freed = FreedStorage::new();
+        let mut removed_claims = vec![];
+        let mut index = from_index as u64;
+        for _ in 0..limit {
+            let keys = self.user_airdrops.keys_as_vector();
+            if index >= keys.len() {
+                break;
+            }
+            let account_id = keys.get(index).unwrap();
+            let user_claim_count = self.internal_get_user_airdrops(&account_id).len();
+            let removed = self.internal_remove_used_claims(&account_id, &mut freed);
+            // when all the user claims were removed, the last user takes this position, so do not advance
+            if removed.len() < user_claim_count {
+                index += 1;
+            }
+            removed_claims.extend(removed);
+        }
+        self.internal_refund_storage(freed);
+        events::Event::emit_claims_removed(&removed_claims);
+        CleanupProgressJSON {
+            next_index: index as u32,
+            user_count: self.user_airdrops.len(),
+            removed_claims: removed_claims.len() as u32,
+        }
+    }
+
+    // a user removes their own used claims, returns how many were removed
+    pub fn prune_my_claims(&mut self) -> u32 {
+        let account_id = env::predecessor_account_id();
+        self.internal_migrate_user(&account_id);
+        let mut freed = FreedStorage::new();
+        let removed = self.internal_remove_used_claims(&account_id, &mut freed);
+        self.internal_refund_storage(freed);
+        events::Event::emit_claims_removed(&removed);
+        removed.len() as u32
+    }
+
+    // receives the freed storage the contract paid for, None keeps it in the contract
+    pub fn set_storage_treasury(&mut self, storage_treasury_id: Option<AccountId>) {
+        self.assert_role(Role::Owner);
+        log!(
+            "{} set the storage treasury to {:?}",
+            env::predecessor_account_id(),
+            storage_treasury_id
+        );
+        self.storage_treasury_id = storage_treasury_id;
+    }
+}
+
+impl GradualReleaseContract {
+    // removes the fully claimed claims of a migrated user, adding the freed storage per payer
+    // returns the removed claims
+    fn internal_remove_used_claims(
+        &mut self,
+        account_id: &AccountId,
+        freed: &mut FreedStorage,
+    ) -> Vec<events::ClaimRemovedData> {
+        let used: Vec<u16> = self
+            .internal_get_user_airdrops(account_id)
+            .into_iter()
+            .filter(|airdrop_index| {
+                let claim = self.internal_get_claim_or_panic(account_id, *airdrop_index);
+                claim.assigned_tokens <= claim.claimed_tokens
+            })
+            .collect();
+        if used.is_empty() {
+            return vec![];
+        }
+        self.internal_remove_claims(account_id, &used, freed)
+    }
+}
@@ -8,10 +8,33 @@ pub const GAS_FOR_FT_TRANSFER: Gas = Gas(50 * TGAS);
 pub const GAS_FOR_AFTER_TRANSFER: Gas = Gas(40 * TGAS);
 pub const GAS_FOR_FT_METADATA: Gas = Gas(5 * TGAS);
 pub const GAS_FOR_REGISTER_AIRDROP_STEP_2: Gas = Gas(10 * TGAS);
+pub const GAS_FOR_FT_BALANCE_OF: Gas = Gas(5 * TGAS);
+pub const GAS_FOR_AFTER_ADD_CLAIMS: Gas = Gas(5 * TGAS);
+
+/// Default delay for queued admin actions and operator handover, 24 hours
+pub const DEFAULT_TIMELOCK_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Max length in bytes of a freeze_user_claim reason, it is stored in every frozen claim
+pub const MAX_FREEZE_REASON_LEN: usize = 256;
+
+/// Max platform fee for sponsored airdrops, 10%
+pub const MAX_PLATFORM_FEE_BP: u16 = 1000;
 
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKey {
     AvailableClaims,
     TotalUnclaimed,
+    Roles,
+    UnallocatedSponsored,
+    PlatformFees,
+    PendingActions,
+    Denylist,
+    Allowlist,
+    Claims,
+    UserAirdrops,
+    Airdrops,
+    PendingActionSlots,
+    StorageDeposits,
+    CancelRefunds,
 }
 
@@ -0,0 +1,256 @@
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+
+use crate::*;
+
+/// NEP-297 events, logged as
+/// `EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"tokens_claimed","data":[...]}`
+///
+/// Every event of a call is logged, even if the call fails later: indexers must skip failed receipts.
+/// Bump EVENT_VERSION when an event or its data changes
+pub const EVENT_STANDARD: &str = "gradual_release_claim";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+/// Max rows per claims_added or claims_removed event, larger lists are logged in several events.
+/// A call still logs at most 16 KiB in total, about 150 claims_added rows
+pub const EVENT_MAX_ROWS: usize = 50;
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    AirdropRegistered(Vec<AirdropRegisteredData>),
+    // one event per EVENT_MAX_ROWS rows, total_amount is the sum of the event rows
+    ClaimsAdded(Vec<ClaimsAddedData>),
+    // update_claim and remove_claim (new_amount 0), while the airdrop is a draft
+    ClaimUpdated(Vec<ClaimUpdatedData>),
+    AirdropFunded(Vec<AirdropData>),
+    AirdropEnabled(Vec<AirdropData>),
+    AirdropDisabled(Vec<AirdropDisabledData>),
+    AirdropArchived(Vec<AirdropData>),
+    AirdropClosed(Vec<AirdropData>),
+    // the airdrop was replaced by a tombstone, after its claims were removed
+    AirdropPurged(Vec<AirdropData>),
+    // the claims of a cancelled airdrop are no longer owed
+    AirdropCancelled(Vec<AirdropData>),
+    ScheduleChanged(Vec<ScheduleChangedData>),
+    TokensClaimed(Vec<ClaimData>),
+    // the token transfer failed, the amount is claimable again
+    ClaimRolledBack(Vec<ClaimData>),
+    // purge_airdrop, remove_cancelled_claims and the cleanup of used claims.
+    // unclaimed_amount is no longer owed, it is 0 for used claims and claims of a cancelled airdrop
+    ClaimsRemoved(Vec<ClaimRemovedData>),
+    // freeze_user_claim and unfreeze_user_claim, one row per claim
+    ClaimFrozen(Vec<ClaimFrozenData>),
+    ClaimUnfrozen(Vec<ClaimUnfrozenData>),
+    ContractPaused(Vec<AccountData>),
+    ContractUnpaused(Vec<AccountData>),
+    RoleGranted(Vec<RoleData>),
+    RoleRevoked(Vec<RoleData>),
+    OwnerChanged(Vec<AccountChangeData>),
+    OperatorChanged(Vec<AccountChangeData>),
+    AirdropManagerChanged(Vec<AirdropManagerChangedData>),
+    // amount is added to the airdrop, platform_fee is kept by the platform
+    SponsorDeposited(Vec<SponsorDepositedData>),
+    // the refund of a sponsored airdrop remainder, after the transfer succeeded
+    SponsorRefunded(Vec<RefundData>),
+    // the refund of a cancelled airdrop that was not sponsored, after the transfer succeeded
+    CancelRefunded(Vec<RefundData>),
+    PlatformFeeChanged(Vec<PlatformFeeChangedData>),
+    // after the transfer succeeded
+    PlatformFeesWithdrawn(Vec<PlatformFeesWithdrawnData>),
+    // one row per account actually added or removed
+    DenylistAdded(Vec<AccountData>),
+    DenylistRemoved(Vec<AccountData>),
+    AllowlistAdded(Vec<AllowlistData>),
+    AllowlistRemoved(Vec<AllowlistData>),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AirdropRegisteredData {
+    pub airdrop_index: u16,
+    pub title: String,
+    pub token_contract: AccountId,
+    pub manager_id: AccountId,
+    pub sponsor_id: Option<AccountId>,
+    pub start_ms: U64,
+    pub end_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimsAddedData {
+    pub airdrop_index: u16,
+    pub total_amount: U128,
+    // [account_id, amount] rows, as in add_claims data but in token units
+    pub claims: Vec<(AccountId, U128)>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimUpdatedData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+    pub old_amount: U128,
+    pub new_amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AirdropData {
+    pub airdrop_index: u16,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AirdropDisabledData {
+    pub airdrop_index: u16,
+    pub freeze_accrual: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScheduleChangedData {
+    pub airdrop_index: u16,
+    pub old_start_ms: U64,
+    pub old_end_ms: U64,
+    pub new_start_ms: U64,
+    pub new_end_ms: U64,
+    pub forced: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimRemovedData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+    pub unclaimed_amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimFrozenData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimUnfrozenData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountData {
+    pub account_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleData {
+    pub account_id: AccountId,
+    pub role: Role,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountChangeData {
+    pub old_account_id: AccountId,
+    pub new_account_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AirdropManagerChangedData {
+    pub airdrop_index: u16,
+    pub old_manager_id: AccountId,
+    pub new_manager_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SponsorDepositedData {
+    pub airdrop_index: u16,
+    pub sponsor_id: AccountId,
+    pub token_contract: AccountId,
+    pub amount: U128,
+    pub platform_fee: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PlatformFeeChangedData {
+    pub old_fee_bp: u16,
+    pub new_fee_bp: u16,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PlatformFeesWithdrawnData {
+    pub token_contract: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AllowlistData {
+    pub airdrop_index: u16,
+    pub account_id: AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+impl Event {
+    pub fn emit(&self) {
+        let event_log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_VERSION,
+            event: self,
+        };
+        log!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&event_log).expect("Can not serialize the event")
+        );
+    }
+
+    // one claims_removed event per EVENT_MAX_ROWS rows
+    pub fn emit_claims_removed(removed: &[ClaimRemovedData]) {
+        Event::emit_chunks(removed, Event::ClaimsRemoved);
+    }
+
+    // one event per EVENT_MAX_ROWS rows, nothing if rows is empty
+    pub fn emit_chunks<T: Clone>(rows: &[T], event: fn(Vec<T>) -> Event) {
+        for rows in rows.chunks(EVENT_MAX_ROWS) {
+            event(rows.to_vec()).emit();
+        }
+    }
+}
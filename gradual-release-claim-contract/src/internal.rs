@@ -7,79 +7,177 @@ use crate::*;
 #[allow(dead_code)]
 pub trait ExtSelf {
     fn after_transfer_token(&mut self, account_id: &AccountId, airdrop_index: u16, amount: U128);
+    fn after_cancel_refund(&mut self, refund_to: &AccountId, airdrop_index: u16, amount: U128);
+    fn after_sponsor_refund(&mut self, airdrop_index: u16, amount: U128);
+    fn after_withdraw_platform_fees(
+        &mut self,
+        token_contract: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    );
 }
 
-pub type VecUserClaims = Vec<UserClaimInfo>;
-
 #[near_bindgen]
 impl GradualReleaseContract {
-    pub(crate) fn assert_only_owner(&self) {
+    pub(crate) fn has_role(&self, account_id: &AccountId, role: Role) -> bool {
+        (*account_id == self.owner_id && roles::OWNER_IMPLICIT_ROLES.contains(&role))
+            || (*account_id == self.operator_id && roles::OPERATOR_IMPLICIT_ROLES.contains(&role))
+            || self.roles.contains(&(role, account_id.clone()))
+    }
+    pub(crate) fn assert_role(&self, role: Role) {
+        let account_id = env::predecessor_account_id();
         require!(
-            self.owner_id == env::predecessor_account_id(),
-            "Only the owner can call this function."
+            self.has_role(&account_id, role),
+            format!("{} does not have the {:?} role", account_id, role)
         );
     }
-    pub(crate) fn assert_operator(&self) {
+    // the airdrop manager, or an account with the role for all airdrops
+    pub(crate) fn assert_airdrop_manager_or_role(&self, airdrop_index: u16, role: Role) {
+        let account_id = env::predecessor_account_id();
         require!(
-            self.operator_id == env::predecessor_account_id(),
-            "Only the operator can call this function."
+            self.airdrops[airdrop_index as u32].live().manager_id == account_id
+                || self.has_role(&account_id, role),
+            format!(
+                "{} is not the manager of airdrop {} and does not have the {:?} role",
+                account_id, airdrop_index, role
+            )
         );
     }
+    pub(crate) fn assert_not_paused(&self) {
+        require!(!self.paused, "The contract is paused");
+    }
 
-    // internal method to get user claims or vec![].
-    pub(crate) fn internal_get_claims(&self, account_id: &AccountId) -> VecUserClaims {
-        self.available_claims
-            .get(&account_id)
-            .unwrap_or(VecUserClaims::new())
+    // tokens the contract owes: unclaimed claims, sponsor deposits not yet in claims, platform fees
+    // and cancel refunds not yet received
+    pub(crate) fn internal_committed_tokens(&self, token_contract: &AccountId) -> u128 {
+        self.total_in_claims_per_token.get(token_contract).unwrap_or(0)
+            + self
+                .unallocated_sponsored_per_token
+                .get(token_contract)
+                .unwrap_or(0)
+            + self.platform_fees_per_token.get(token_contract).unwrap_or(0)
+            + self.cancel_refunds_per_token.get(token_contract).unwrap_or(0)
     }
-    pub(crate) fn internal_get_claims_or_panic(&self, account_id: &AccountId) -> VecUserClaims {
-        match self.available_claims.get(&account_id) {
-            Some(a) => a,
-            _ => panic!("{} has no claims", account_id),
+
+    // requires the attached deposit to cover the storage used since storage_before,
+    // adds the cost to the payer storage ledger and refunds the excess to the payer.
+    // The new rows record their payer, freed storage is refunded to it up to its ledger
+    pub(crate) fn internal_pay_storage(
+        &mut self,
+        storage_before: u64,
+        attached_deposit: u128,
+        payer_id: &AccountId,
+    ) {
+        let storage_cost =
+            env::storage_usage().saturating_sub(storage_before) as u128 * env::storage_byte_cost();
+        require!(
+            attached_deposit >= storage_cost,
+            format!(
+                "The attached deposit {} does not cover the storage cost {}",
+                attached_deposit, storage_cost
+            )
+        );
+        if storage_cost > 0 {
+            let paid = self.storage_deposits.get(payer_id).unwrap_or(0);
+            self.storage_deposits.insert(payer_id, &(paid + storage_cost));
+        }
+        let refund = attached_deposit - storage_cost;
+        if refund > 1 {
+            Promise::new(payer_id.clone()).transfer(refund);
         }
     }
 
-    // distributes stNEAR or mpDAO between existent voters
-    // called from ft_on_transfer
+    // returns the storage deposit of the freed bytes to each payer, up to its ledger, the rest to the storage treasury
+    pub(crate) fn internal_refund_storage(&mut self, freed: FreedStorage) {
+        for (payer_id, freed_bytes) in freed {
+            // None was paid by the contract
+            let refund = match &payer_id {
+                Some(payer_id) => {
+                    let paid = self.storage_deposits.get(payer_id).unwrap_or(0);
+                    let refund =
+                        std::cmp::min(freed_bytes as u128 * env::storage_byte_cost(), paid);
+                    if paid > refund {
+                        self.storage_deposits.insert(payer_id, &(paid - refund));
+                    } else {
+                        self.storage_deposits.remove(payer_id);
+                    }
+                    refund
+                }
+                None => 0,
+            };
+            self.internal_send_freed_storage(payer_id.as_ref(), refund, freed_bytes);
+        }
+    }
+
+    // sends refund to the storage payer, and the rest of the cost of freed_bytes to the storage treasury
+    pub(crate) fn internal_send_freed_storage(
+        &self,
+        storage_payer_id: Option<&AccountId>,
+        refund: u128,
+        freed_bytes: u64,
+    ) {
+        if let Some(storage_payer_id) = storage_payer_id {
+            if refund > 0 {
+                Promise::new(storage_payer_id.clone()).transfer(refund);
+            }
+        }
+        // the rest was paid by the contract
+        let freed_cost = freed_bytes as u128 * env::storage_byte_cost();
+        if let Some(storage_treasury_id) = &self.storage_treasury_id {
+            if freed_cost > refund {
+                Promise::new(storage_treasury_id.clone()).transfer(freed_cost - refund);
+            }
+        }
+    }
+
+    // creates the user claims for an airdrop, or tops-up existing ones
+    // called from add_claims (or add_claims_step_2 if the airdrop is enabled)
+    // storage_payer_id pays the storage of the new claims
     pub(crate) fn internal_create_claims(
         &mut self,
         airdrop_index: u16,
         total_amount: u128,
         claims_array: Vec<(String, String)>,
+        storage_payer_id: &AccountId,
     ) {
-        let airdrop = &mut self.airdrops[airdrop_index as usize];
+        let airdrop = self.airdrops[airdrop_index as u32].live();
         assert!(
-            airdrop.status_code == airdrop::status_code::DISABLED,
-            "Airdrop {} is nor disabled. Can not add more claims",
-            airdrop_index
+            !matches!(
+                airdrop.status,
+                airdrop::AirdropStatus::Ended | airdrop::AirdropStatus::Closed
+            ),
+            "Airdrop {} is {:?}. Can not add more claims",
+            airdrop_index,
+            airdrop.status
         );
+        let token_decimals = airdrop.token_decimals;
         let mut total_distributed = 0;
-        for item in claims_array {
-            let account_id = &AccountId::new_unchecked(item.0);
-            let claims = &mut self
-                .available_claims
-                .get(account_id)
-                .unwrap_or(VecUserClaims::new());
-
-            if claims
-                .into_iter()
-                .any(|claim| claim.airdrop_index == airdrop_index)
-            {
-                panic!(
-                    "{} already has a claim for airdrop {}",
-                    account_id, airdrop_index
-                );
-            }
-            let amount = parse_token_amount(&item.1, airdrop.token_decimals);
-            claims.push(UserClaimInfo {
-                airdrop_index,
-                assigned_tokens: amount,
-                claimed_tokens: 0,
-            });
+        let mut added = Vec::with_capacity(claims_array.len());
+        for (row, item) in claims_array.iter().enumerate() {
+            // the whole batch fails on the first bad row, see validate_claims to check a list first
+            let (account_id, amount) = parse_claim_row(item, token_decimals)
+                .unwrap_or_else(|err| panic!("ERR: data[{}]: {}", row, err));
+            self.assert_not_denylisted(&account_id);
+            self.internal_migrate_user(&account_id);
+            let claim = match self.internal_get_claim(&account_id, airdrop_index) {
+                // top-up, the user already has a claim for this airdrop
+                Some(mut claim) => {
+                    claim.assigned_tokens += amount;
+                    claim
+                }
+                None => UserClaimInfo {
+                    airdrop_index,
+                    assigned_tokens: amount,
+                    claimed_tokens: 0,
+                    frozen_reason: None,
+                    storage_payer_id: Some(storage_payer_id.clone()),
+                },
+            };
             // save
-            self.available_claims.insert(&account_id, &claims);
+            self.internal_save_claim(&account_id, &claim);
             // sum total distributed
             total_distributed += amount;
+            added.push((account_id, U128(amount)));
         }
 
         assert!(
@@ -89,6 +187,23 @@ impl GradualReleaseContract {
             total_amount
         );
 
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        if airdrop.sponsor_id.is_some() {
+            // sponsored airdrops can only distribute what the sponsor deposited
+            assert!(
+                airdrop.total_distributed + total_distributed <= airdrop.deposited_tokens,
+                "ERR: airdrop {} deposited {} < total distributed {}",
+                airdrop_index,
+                airdrop.deposited_tokens,
+                airdrop.total_distributed + total_distributed
+            );
+            sub_from_token_total(
+                &mut self.unallocated_sponsored_per_token,
+                &airdrop.token_contract,
+                total_distributed,
+            );
+        }
+
         airdrop.total_distributed += total_distributed;
 
         // update total_in_claims UnorderedMap
@@ -100,6 +215,145 @@ impl GradualReleaseContract {
             &airdrop.token_contract,
             &(current_amount + total_distributed),
         );
+        for rows in added.chunks(events::EVENT_MAX_ROWS) {
+            events::Event::ClaimsAdded(vec![events::ClaimsAddedData {
+                airdrop_index,
+                total_amount: U128(rows.iter().map(|(_, amount)| amount.0).sum()),
+                claims: rows.to_vec(),
+            }])
+            .emit();
+        }
+    }
+
+    // changes the assigned amount of a user claim, or removes the claim if new_amount is None
+    // keeps airdrop.total_distributed and total_in_claims_per_token consistent
+    pub(crate) fn internal_update_claim(
+        &mut self,
+        airdrop_index: u16,
+        account_id: &AccountId,
+        new_amount: Option<u128>,
+    ) {
+        assert!(
+            self.airdrops[airdrop_index as u32].live().status == airdrop::AirdropStatus::Draft,
+            "Airdrop {} is not a draft. Can not change claims",
+            airdrop_index
+        );
+        self.internal_migrate_user(account_id);
+        let mut claim = self.internal_get_claim_or_panic(account_id, airdrop_index);
+        let old_amount = claim.assigned_tokens;
+        let new_amount = match new_amount {
+            Some(amount) => {
+                assert!(
+                    amount >= claim.claimed_tokens,
+                    "new amount {} is less than already claimed {}",
+                    amount,
+                    claim.claimed_tokens
+                );
+                claim.assigned_tokens = amount;
+                // save
+                self.internal_save_claim(account_id, &claim);
+                amount
+            }
+            None => {
+                assert!(
+                    claim.claimed_tokens == 0,
+                    "{} already claimed {} from airdrop {}",
+                    account_id,
+                    claim.claimed_tokens,
+                    airdrop_index
+                );
+                let mut freed = FreedStorage::new();
+                self.internal_remove_claims(account_id, &[airdrop_index], &mut freed);
+                self.internal_refund_storage(freed);
+                0
+            }
+        };
+
+        // update totals
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        airdrop.total_distributed = airdrop.total_distributed - old_amount + new_amount;
+        if airdrop.sponsor_id.is_some() {
+            assert!(
+                airdrop.total_distributed <= airdrop.deposited_tokens,
+                "ERR: airdrop {} deposited {} < total distributed {}",
+                airdrop_index,
+                airdrop.deposited_tokens,
+                airdrop.total_distributed
+            );
+            add_to_token_total(
+                &mut self.unallocated_sponsored_per_token,
+                &airdrop.token_contract,
+                old_amount,
+            );
+            sub_from_token_total(
+                &mut self.unallocated_sponsored_per_token,
+                &airdrop.token_contract,
+                new_amount,
+            );
+        }
+        let current_amount = self
+            .total_in_claims_per_token
+            .get(&airdrop.token_contract)
+            .unwrap_or(0);
+        self.total_in_claims_per_token.insert(
+            &airdrop.token_contract,
+            &(current_amount - old_amount + new_amount),
+        );
+
+        events::Event::ClaimUpdated(vec![events::ClaimUpdatedData {
+            airdrop_index,
+            account_id: account_id.clone(),
+            old_amount: U128(old_amount),
+            new_amount: U128(new_amount),
+        }])
+        .emit();
+    }
+
+    // sets or clears frozen_reason in the user claims of one airdrop, or all of them
+    pub(crate) fn internal_set_claims_frozen(
+        &mut self,
+        account_id: &AccountId,
+        airdrop_index: Option<u16>,
+        frozen_reason: Option<String>,
+    ) {
+        self.internal_migrate_user(account_id);
+        let mut frozen = vec![];
+        let mut unfrozen = vec![];
+        for index in self
+            .internal_get_user_airdrops(account_id)
+            .into_iter()
+            .filter(|index| airdrop_index.is_none() || airdrop_index == Some(*index))
+        {
+            let mut claim = self.internal_get_claim_or_panic(account_id, index);
+            claim.frozen_reason = frozen_reason.clone();
+            self.internal_save_claim(account_id, &claim);
+            match &frozen_reason {
+                Some(reason) => frozen.push(events::ClaimFrozenData {
+                    airdrop_index: index,
+                    account_id: account_id.clone(),
+                    reason: reason.clone(),
+                }),
+                None => unfrozen.push(events::ClaimUnfrozenData {
+                    airdrop_index: index,
+                    account_id: account_id.clone(),
+                }),
+            }
+        }
+        let count = frozen.len() + unfrozen.len();
+        assert!(count > 0, "{} has no claim for airdrop {:?}", account_id, airdrop_index);
+        log!(
+            "{} {} {} claims of {}, airdrop {:?}",
+            env::predecessor_account_id(),
+            if frozen_reason.is_some() { "froze" } else { "unfroze" },
+            count,
+            account_id,
+            airdrop_index
+        );
+        if frozen_reason.is_some() {
+            events::Event::ClaimFrozen(frozen).emit();
+        } else {
+            events::Event::ClaimUnfrozen(unfrozen).emit();
+        }
     }
 
     // before transfer
@@ -108,17 +362,21 @@ impl GradualReleaseContract {
         account_id: &AccountId,
         airdrop_index: u16,
     ) -> u128 {
-        let user_claims = &mut self.internal_get_claims_or_panic(account_id);
-        let airdrop = &mut self.airdrops[airdrop_index as usize];
-        assert!(airdrop.is_enabled(), "Airdrop {} is not enabled", airdrop_index);
-        let claim = match user_claims
-            .iter_mut()
-            .find(|i| i.airdrop_index == airdrop_index)
-        {
-            Some(c) => c, // claim is found
-            None => panic!("{} has no claim for airdrop {}", account_id, airdrop_index),
-        };
-        let available_to_claim_now = claim.available_now(&airdrop.release_schedule);
+        assert!(
+            self.airdrops[airdrop_index as u32].live().is_enabled(),
+            "Airdrop {} is not enabled",
+            airdrop_index
+        );
+        self.internal_migrate_user(account_id);
+        let mut claim = self.internal_get_claim_or_panic(account_id, airdrop_index);
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        if let Some(reason) = &claim.frozen_reason {
+            panic!(
+                "The claim of {} for airdrop {} is frozen: {}",
+                account_id, airdrop_index, reason
+            );
+        }
+        let available_to_claim_now = claim.available_now(&airdrop.effective_schedule());
         if available_to_claim_now == 0 {
             panic!(
                 "0 available now. {} assigned:{} claimed:{}",
@@ -126,18 +384,19 @@ impl GradualReleaseContract {
             );
         };
         claim.claimed_tokens += available_to_claim_now;
-        // save
-        self.available_claims.insert(account_id, &user_claims);
         // update total claimed for the airdrop
         airdrop.total_claimed += available_to_claim_now;
+        let token_contract = airdrop.token_contract.clone();
+        // save
+        self.internal_save_claim(account_id, &claim);
 
         // remove from total in claims
         let current_amount = self
             .total_in_claims_per_token
-            .get(&airdrop.token_contract)
+            .get(&token_contract)
             .unwrap_or(0);
         self.total_in_claims_per_token.insert(
-            &airdrop.token_contract,
+            &token_contract,
             &(current_amount - available_to_claim_now),
         );
 
@@ -152,19 +411,12 @@ impl GradualReleaseContract {
         airdrop_index: u16,
         amount: u128,
     ) {
-        let user_claims = &mut self.internal_get_claims_or_panic(account_id);
-        let airdrop = &mut self.airdrops[airdrop_index as usize];
-        let claim = match user_claims
-            .iter_mut()
-            .find(|i| i.airdrop_index == airdrop_index)
-        {
-            Some(c) => c, // claim is found
-            None => panic!("{} has no claim for airdrop {}", account_id, airdrop_index),
-        };
+        let mut claim = self.internal_get_claim_or_panic(account_id, airdrop_index);
         // restore
         claim.claimed_tokens -= amount;
         // save
-        self.available_claims.insert(account_id, &user_claims);
+        self.internal_save_claim(account_id, &claim);
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
         // undo total claimed sum for the airdrop
         airdrop.total_claimed -= amount;
 
@@ -181,8 +433,9 @@ impl GradualReleaseContract {
     }
 
     pub(crate) fn internal_claim(&mut self, airdrop_index: u16, account_id: &AccountId) -> Promise {
+        self.assert_can_claim(airdrop_index, account_id);
         let amount = self.remove_claimable_amount(&account_id, airdrop_index);
-        let airdrop = &self.airdrops[airdrop_index as usize];
+        let airdrop = self.airdrops[airdrop_index as u32].live();
         ext_ft_core::ext(airdrop.token_contract.clone())
             .with_static_gas(GAS_FOR_FT_TRANSFER)
             .with_attached_deposit(1)
@@ -200,29 +453,198 @@ impl GradualReleaseContract {
 
     #[private]
     pub fn after_transfer_token(&mut self, account_id: &AccountId, airdrop_index: u16, amount: U128) {
-        let airdrop = &self.airdrops[airdrop_index as usize];
-        let amount = amount.0;
+        let claim = events::ClaimData {
+            airdrop_index,
+            account_id: account_id.clone(),
+            amount,
+        };
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => events::Event::TokensClaimed(vec![claim]).emit(),
+            PromiseResult::Failed => {
+                // ROLLBACK
+                self.re_add_claimable_amount(account_id, airdrop_index, amount.0);
+                events::Event::ClaimRolledBack(vec![claim]).emit();
+            }
+        };
+    }
+
+    // only an airdrop that was never launched nor claimed can be cancelled
+    pub(crate) fn assert_can_cancel(&self, airdrop_index: u16) {
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        assert!(
+            matches!(
+                airdrop.status,
+                airdrop::AirdropStatus::Draft | airdrop::AirdropStatus::Funded
+            ),
+            "Airdrop {} was already launched",
+            airdrop_index
+        );
+        assert!(
+            airdrop.total_claimed == 0,
+            "Airdrop {} already had claims",
+            airdrop_index
+        );
+    }
+
+    // closes the airdrop and removes its claims from total_in_claims_per_token
+    pub(crate) fn internal_cancel_airdrop(&mut self, airdrop_index: u16) {
+        self.assert_can_cancel(airdrop_index);
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        airdrop.change_status(airdrop::AirdropStatus::Closed);
+        airdrop.cancelled = true;
+        events::Event::AirdropCancelled(vec![events::AirdropData { airdrop_index }]).emit();
+
+        // remove from total in claims
+        let current_amount = self
+            .total_in_claims_per_token
+            .get(&airdrop.token_contract)
+            .unwrap_or(0);
+        self.total_in_claims_per_token.insert(
+            &airdrop.token_contract,
+            &(current_amount - airdrop.total_distributed),
+        );
+    }
+
+    // the amount stays committed until the transfer succeeds, a failed transfer can be retried
+    pub(crate) fn internal_refund_cancelled(
+        &mut self,
+        airdrop_index: u16,
+        refund_to: &AccountId,
+        amount: u128,
+    ) -> Promise {
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        airdrop.cancel_refund = Some(airdrop::CancelRefund {
+            refund_to: refund_to.clone(),
+            amount,
+            failed: false,
+        });
+        add_to_token_total(
+            &mut self.cancel_refunds_per_token,
+            &airdrop.token_contract,
+            amount,
+        );
+        ext_ft_core::ext(airdrop.token_contract.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(1)
+            .ft_transfer(
+                refund_to.clone(),
+                U128::from(amount),
+                Some(format!("refund of cancelled {}", airdrop.title)), // Memo
+            )
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_AFTER_TRANSFER)
+                    .after_cancel_refund(refund_to, airdrop_index, U128::from(amount)),
+            )
+    }
+
+    #[private]
+    pub fn after_cancel_refund(&mut self, refund_to: &AccountId, airdrop_index: u16, amount: U128) {
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
         match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
             PromiseResult::Successful(_) => {
+                airdrop.cancel_refund = None;
+                sub_from_token_total(
+                    &mut self.cancel_refunds_per_token,
+                    &airdrop.token_contract,
+                    amount.0,
+                );
                 log!(
-                    "{} claimed {} {} airdrop_index:{}",
-                    account_id,
-                    amount,
+                    "{} refunded {} {} cancelled airdrop_index:{}",
+                    refund_to,
+                    amount.0,
                     airdrop.token_symbol,
                     airdrop_index
                 );
+                events::Event::CancelRefunded(vec![events::RefundData {
+                    airdrop_index,
+                    account_id: refund_to.clone(),
+                    amount,
+                }])
+                .emit();
             }
             PromiseResult::Failed => {
                 log!(
-                    "FAIL: while claiming {} {} airdrop_index:{} user {}",
+                    "FAIL: while refunding {} {} cancelled airdrop_index:{} to {}",
+                    amount.0,
+                    airdrop.token_symbol,
+                    airdrop_index,
+                    refund_to,
+                );
+                // the tokens stay committed, can be retried with retry_cancel_refund
+                airdrop.cancel_refund.as_mut().unwrap().failed = true;
+            }
+        };
+    }
+
+    // returns to the sponsor the deposited tokens not assigned to users
+    pub(crate) fn internal_refund_sponsor(&mut self, airdrop_index: u16) -> PromiseOrValue<()> {
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        let sponsor_id = airdrop.sponsor_id.clone().unwrap();
+        let amount = airdrop.unallocated_tokens();
+        if amount == 0 {
+            return PromiseOrValue::Value(());
+        }
+        airdrop.deposited_tokens -= amount;
+        airdrop.pending_sponsor_refund = amount;
+        sub_from_token_total(
+            &mut self.unallocated_sponsored_per_token,
+            &airdrop.token_contract,
+            amount,
+        );
+        ext_ft_core::ext(airdrop.token_contract.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(1)
+            .ft_transfer(
+                sponsor_id,
+                U128::from(amount),
+                Some(format!("remainder of {}", airdrop.title)), // Memo
+            )
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_AFTER_TRANSFER)
+                    .after_sponsor_refund(airdrop_index, U128::from(amount)),
+            )
+            .into()
+    }
+
+    #[private]
+    pub fn after_sponsor_refund(&mut self, airdrop_index: u16, amount: U128) {
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        airdrop.pending_sponsor_refund = 0;
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {
+                log!(
+                    "{} refunded {} {} sponsored airdrop_index:{}",
+                    airdrop.sponsor_id.as_ref().unwrap(),
+                    amount.0,
+                    airdrop.token_symbol,
+                    airdrop_index
+                );
+                events::Event::SponsorRefunded(vec![events::RefundData {
+                    airdrop_index,
+                    account_id: airdrop.sponsor_id.clone().unwrap(),
                     amount,
+                }])
+                .emit();
+            }
+            PromiseResult::Failed => {
+                log!(
+                    "FAIL: while refunding {} {} sponsored airdrop_index:{}",
+                    amount.0,
                     airdrop.token_symbol,
                     airdrop_index,
-                    account_id,
                 );
-                // ROLLBACK
-                self.re_add_claimable_amount(account_id, airdrop_index, amount);
+                // ROLLBACK, can be retried with refund_sponsor_remainder
+                airdrop.deposited_tokens += amount.0;
+                add_to_token_total(
+                    &mut self.unallocated_sponsored_per_token,
+                    &airdrop.token_contract,
+                    amount.0,
+                );
             }
         };
     }
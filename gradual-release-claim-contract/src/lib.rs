@@ -6,35 +6,59 @@ use near_contract_standards::fungible_token::{
 use near_sdk::{
     assert_one_yocto,
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::unordered_map::UnorderedMap,
+    collections::{unordered_map::UnorderedMap, LookupMap, UnorderedSet},
     env, ext_contract,
     json_types::{U128, U64},
-    log, near_bindgen, require, AccountId, PanicOnDefault, Promise,
+    log, near_bindgen, require, store::Vector, AccountId, PanicOnDefault, Promise,
+    PromiseError, PromiseOrValue,
 };
+use roles::Role;
 use user_claim_info::UserClaimInfo;
 
+mod access_lists;
 mod airdrop;
+mod audit;
+mod claims;
+mod cleanup;
 mod constants;
+mod events;
 mod internal;
 mod migrate;
+mod purge;
+mod roles;
+mod sponsored;
+mod timelock;
+mod upgrade;
 mod user_claim_info;
 mod utils;
 mod view;
 
 pub type Token = AccountId;
+// freed bytes per storage payer, None for storage paid by the contract. See internal_refund_storage
+pub type FreedStorage = std::collections::BTreeMap<Option<AccountId>, u64>;
 
 #[ext_contract(ext_self)]
 #[allow(dead_code)]
 trait ExtSelf {
-    fn register_airdrop_step_2(
+    fn register_airdrop_step_2(&mut self, registration: airdrop::AirdropRegistration)
+        -> Option<u16>;
+
+    fn add_claims_step_2(
         &mut self,
-        title: String,
-        token_contract: AccountId,
-        start_timestamp_ms: U64,
-        end_timestamp_ms: U64,
-    ) -> u16;
+        airdrop_index: u16,
+        total_amount: U128,
+        data: Vec<(String, String)>,
+        storage_payer_id: AccountId,
+        storage_deposit: U128,
+    ) -> bool;
+
+    fn after_add_claims(&mut self, storage_payer_id: AccountId, storage_deposit: U128);
+
+    fn confirm_funding_step_2(&mut self, airdrop_index: u16);
 
     fn enable_airdrop_step_2(&mut self, airdrop_index: u16);
+
+    fn cancel_airdrop_step_2(&mut self, airdrop_index: u16, refund_to: AccountId);
 }
 
 #[near_bindgen]
@@ -43,126 +67,617 @@ trait ExtSelf {
 pub struct GradualReleaseContract {
     pub owner_id: AccountId,
     pub operator_id: AccountId,
+    // nominees, they must call accept_ownership / accept_operator
+    pub pending_owner_id: Option<AccountId>,
+    pub pending_operator_id: Option<AccountId>,
+    pub operator_accept_after_ms: u64,
+    // roles granted to other accounts, see roles.rs
+    pub roles: UnorderedSet<(Role, AccountId)>,
 
     // period is the same for everyone
-    pub airdrops: Vec<airdrop::Airdrop>,
+    // lazily loaded, a call only reads the airdrops it uses
+    pub airdrops: Vector<airdrop::AirdropEntry>,
 
-    pub available_claims: UnorderedMap<AccountId, Vec<UserClaimInfo>>, // claimable tokens per user
+    // emergency stop: blocks claims and operator methods, views still work
+    pub paused: bool,
+
+    // claimable tokens per user and airdrop, see claims.rs
+    pub claims: LookupMap<(AccountId, u16), UserClaimInfo>,
+    pub user_airdrops: UnorderedMap<AccountId, Vec<u16>>, // airdrop indexes of each user's claims
+    pub legacy_claims: UnorderedMap<AccountId, Vec<claims::LegacyUserClaimInfo>>, // v2.0.0 layout, emptied by migrate_claims
     pub total_in_claims_per_token: UnorderedMap<Token, u128>, // currently unclaimed -- increase on add_claims, decrease on claim
+
+    // sponsored airdrops, see sponsored.rs
+    pub platform_fee_bp: u16,
+    pub unallocated_sponsored_per_token: UnorderedMap<Token, u128>, // deposited by sponsors, not yet in claims
+    pub platform_fees_per_token: UnorderedMap<Token, u128>, // collected from sponsor deposits, not yet withdrawn
+    pub cancel_refunds_per_token: UnorderedMap<Token, u128>, // refunds of cancel_airdrop sent or failed, not yet received
+
+    // queued admin actions on live airdrops, see timelock.rs
+    pub timelock_delay_ms: u64,
+    pub next_action_id: u64,
+    pub pending_actions: UnorderedMap<u64, timelock::PendingAction>,
+    // the pending action id of each (kind, airdrop_index)
+    pub pending_action_slots: LookupMap<timelock::ActionSlot, u64>,
+
+    // see access_lists.rs
+    pub denylist: UnorderedMap<AccountId, AccountId>, // the value paid the entry storage
+    pub allowlist: LookupMap<(u16, AccountId), AccountId>, // the value paid the entry storage
+
+    // NEAR paid for the storage of claims and list entries and not yet refunded, per payer
+    pub storage_deposits: LookupMap<AccountId, u128>,
+
+    // receives freed storage that was paid by the contract, see cleanup.rs
+    pub storage_treasury_id: Option<AccountId>,
 }
 
 #[near_bindgen]
 impl GradualReleaseContract {
+    // timelock_delay_ms defaults to 24 hours
     #[init]
-    pub fn new(owner_id: AccountId, operator_id: AccountId) -> Self {
+    pub fn new(owner_id: AccountId, operator_id: AccountId, timelock_delay_ms: Option<U64>) -> Self {
         require!(!env::state_exists(), "The contract is already initialized");
+        migrate::write_state_version();
         Self {
             owner_id,
             operator_id,
-            airdrops: vec![],
-            available_claims: UnorderedMap::new(StorageKey::AvailableClaims),
+            pending_owner_id: None,
+            pending_operator_id: None,
+            operator_accept_after_ms: 0,
+            roles: UnorderedSet::new(StorageKey::Roles),
+            airdrops: Vector::new(StorageKey::Airdrops),
+            paused: false,
+            claims: LookupMap::new(StorageKey::Claims),
+            user_airdrops: UnorderedMap::new(StorageKey::UserAirdrops),
+            legacy_claims: UnorderedMap::new(StorageKey::AvailableClaims),
             total_in_claims_per_token: UnorderedMap::new(StorageKey::TotalUnclaimed),
+            platform_fee_bp: 0,
+            unallocated_sponsored_per_token: UnorderedMap::new(StorageKey::UnallocatedSponsored),
+            platform_fees_per_token: UnorderedMap::new(StorageKey::PlatformFees),
+            cancel_refunds_per_token: UnorderedMap::new(StorageKey::CancelRefunds),
+            timelock_delay_ms: timelock_delay_ms.map_or(DEFAULT_TIMELOCK_DELAY_MS, |delay| delay.0),
+            next_action_id: 0,
+            pending_actions: UnorderedMap::new(StorageKey::PendingActions),
+            pending_action_slots: LookupMap::new(StorageKey::PendingActionSlots),
+            denylist: UnorderedMap::new(StorageKey::Denylist),
+            allowlist: LookupMap::new(StorageKey::Allowlist),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            storage_treasury_id: None,
         }
     }
 
     // ***************
     // * owner config
     // ***************
+    // two-step handover: the owner proposes, the new account accepts
+    #[payable]
+    pub fn propose_owner(&mut self, owner_id: AccountId) {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        log!("{} proposed {} as owner", env::predecessor_account_id(), owner_id);
+        self.pending_owner_id = Some(owner_id);
+    }
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.pending_owner_id.as_ref() == Some(&account_id),
+            "Only the proposed owner can accept"
+        );
+        events::Event::OwnerChanged(vec![events::AccountChangeData {
+            old_account_id: self.owner_id.clone(),
+            new_account_id: account_id.clone(),
+        }])
+        .emit();
+        self.owner_id = account_id;
+        self.pending_owner_id = None;
+    }
+    #[payable]
+    pub fn cancel_owner_proposal(&mut self) {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        require!(self.pending_owner_id.is_some(), "No owner proposal");
+        self.pending_owner_id = None;
+    }
+
     #[payable]
-    pub fn set_operator_id(&mut self, operator_id: AccountId) {
+    pub fn propose_operator(&mut self, operator_id: AccountId) {
         assert_one_yocto();
-        self.assert_only_owner();
-        self.operator_id = operator_id;
+        self.assert_role(Role::Owner);
+        log!(
+            "{} proposed {} as operator",
+            env::predecessor_account_id(),
+            operator_id
+        );
+        self.pending_operator_id = Some(operator_id);
+        self.operator_accept_after_ms = get_current_epoch_millis() + self.timelock_delay_ms;
+    }
+    // the nominee can accept after the timelock_delay_ms of the proposal
+    #[payable]
+    pub fn accept_operator(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.pending_operator_id.as_ref() == Some(&account_id),
+            "Only the proposed operator can accept"
+        );
+        assert!(
+            get_current_epoch_millis() >= self.operator_accept_after_ms,
+            "The operator change can not be accepted before {}",
+            self.operator_accept_after_ms
+        );
+        events::Event::OperatorChanged(vec![events::AccountChangeData {
+            old_account_id: self.operator_id.clone(),
+            new_account_id: account_id.clone(),
+        }])
+        .emit();
+        self.operator_id = account_id;
+        self.pending_operator_id = None;
+    }
+    #[payable]
+    pub fn cancel_operator_proposal(&mut self) {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        require!(self.pending_operator_id.is_some(), "No operator proposal");
+        self.pending_operator_id = None;
+    }
+
+    #[payable]
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        require!(
+            self.roles.insert(&(role, account_id.clone())),
+            "The account already has the role"
+        );
+        events::Event::RoleGranted(vec![events::RoleData { account_id, role }]).emit();
+    }
+    #[payable]
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        require!(
+            self.roles.remove(&(role, account_id.clone())),
+            "The role was not granted to the account"
+        );
+        events::Event::RoleRevoked(vec![events::RoleData { account_id, role }]).emit();
+    }
+
+    // emergency stop for all airdrops
+    #[payable]
+    pub fn pause(&mut self) {
+        assert_one_yocto();
+        self.assert_role(Role::Pauser);
+        require!(!self.paused, "The contract is already paused");
+        self.paused = true;
+        events::Event::ContractPaused(vec![events::AccountData {
+            account_id: env::predecessor_account_id(),
+        }])
+        .emit();
     }
     #[payable]
-    pub fn set_owner_id(&mut self, owner_id: AccountId) {
+    pub fn unpause(&mut self) {
         assert_one_yocto();
-        self.assert_only_owner();
-        self.owner_id = owner_id;
+        self.assert_role(Role::Owner);
+        require!(self.paused, "The contract is not paused");
+        self.paused = false;
+        events::Event::ContractUnpaused(vec![events::AccountData {
+            account_id: env::predecessor_account_id(),
+        }])
+        .emit();
     }
 
     #[payable]
     // timestamp in milliseconds
-    // returns airdrop index
+    // manager_id can manage this airdrop, defaults to the caller
+    // the attached NEAR pays the airdrop storage, the excess is refunded
+    // returns airdrop index, or None if the registration failed and the deposit was refunded
     pub fn register_airdrop(
         &mut self,
         title: String,
         token_contract: AccountId,
         start_timestamp_ms: U64,
         end_timestamp_ms: U64,
+        manager_id: Option<AccountId>,
     ) -> Promise {
-        self.assert_operator();
-        assert_one_yocto();
+        self.assert_role(Role::AirdropAdmin);
+        self.assert_not_paused();
+        self.internal_register_airdrop(airdrop::AirdropRegistration {
+            title,
+            token_contract,
+            start_timestamp_ms,
+            end_timestamp_ms,
+            manager_id: manager_id.unwrap_or_else(env::predecessor_account_id),
+            sponsored: false,
+            storage_payer_id: env::predecessor_account_id(),
+            storage_deposit: U128::from(env::attached_deposit()),
+        })
+    }
+    pub(crate) fn internal_register_airdrop(
+        &self,
+        registration: airdrop::AirdropRegistration,
+    ) -> Promise {
+        require!(
+            registration.storage_deposit.0 > 0,
+            "Attach NEAR to pay the airdrop storage"
+        );
+        require!(
+            self.airdrops.len() < u16::MAX as u32,
+            "The contract can not register more airdrops"
+        );
         assert!(
-            start_timestamp_ms.0 <= end_timestamp_ms.0,
+            registration.start_timestamp_ms.0 <= registration.end_timestamp_ms.0,
             "Start timestamp_ms must be before end timestamp_ms"
         );
         // get token metadata to store token symbol and decimals
-        ext_ft_metadata::ext(token_contract.clone())
+        ext_ft_metadata::ext(registration.token_contract.clone())
             .with_static_gas(GAS_FOR_FT_METADATA)
             .ft_metadata()
             .then(
                 ext_self::ext(env::current_account_id())
                     .with_static_gas(GAS_FOR_REGISTER_AIRDROP_STEP_2)
-                    .register_airdrop_step_2(
-                        title,
-                        token_contract,
-                        start_timestamp_ms,
-                        end_timestamp_ms,
-                    ),
+                    .register_airdrop_step_2(registration),
             )
     }
     // after obtaining token metadata
+    // on failure the storage deposit is refunded and it returns None
     #[private]
     pub fn register_airdrop_step_2(
         &mut self,
-        title: String,
-        token_contract: AccountId,
-        start_timestamp_ms: U64,
-        end_timestamp_ms: U64,
-        #[callback] metadata: FungibleTokenMetadata,
-    ) -> u16 {
-        self.airdrops.push(airdrop::Airdrop {
-            status_code: airdrop::status_code::DISABLED,
+        registration: airdrop::AirdropRegistration,
+        #[callback_result] metadata: Result<FungibleTokenMetadata, PromiseError>,
+    ) -> Option<u16> {
+        let airdrop::AirdropRegistration {
+            title,
+            token_contract,
+            start_timestamp_ms,
+            end_timestamp_ms,
+            manager_id,
+            sponsored,
+            storage_payer_id,
+            storage_deposit,
+        } = registration;
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                log!("ERR: can not get ft_metadata from {}", token_contract);
+                Promise::new(storage_payer_id).transfer(storage_deposit.0);
+                return None;
+            }
+        };
+        // another registration may have taken the last index meanwhile
+        if self.airdrops.len() >= u16::MAX as u32 {
+            log!("ERR: the contract can not register more airdrops");
+            Promise::new(storage_payer_id).transfer(storage_deposit.0);
+            return None;
+        }
+        let storage_before = env::storage_usage();
+        self.airdrops.push(airdrop::AirdropEntry::Live(airdrop::Airdrop {
+            status: airdrop::AirdropStatus::Draft,
             title,
             token_contract,
+            sponsor_id: if sponsored { Some(manager_id.clone()) } else { None },
+            manager_id,
+            deposited_tokens: 0,
             token_symbol: metadata.symbol,
             token_decimals: metadata.decimals,
             release_schedule: airdrop::TimestampPeriod {
                 start_ms: start_timestamp_ms.0,
                 end_ms: end_timestamp_ms.0,
             },
+            frozen_since_ms: None,
+            total_frozen_ms: 0,
+            allowlist_required: false,
             total_distributed: 0,
             total_claimed: 0,
-        });
+            total_reclaimed: 0,
+            pending_sponsor_refund: 0,
+            cancel_refund: None,
+            claim_count: 0,
+            status_history: vec![airdrop::StatusChange::now(airdrop::AirdropStatus::Draft)],
+            cancelled: false,
+            storage_payer_id: storage_payer_id.clone(),
+            storage_deposit: 0,
+        }));
+        // writes the new airdrop now, to measure it
+        self.airdrops.flush();
 
-        self.airdrops.len() as u16 - 1
+        let storage_cost =
+            (env::storage_usage() - storage_before) as u128 * env::storage_byte_cost();
+        if storage_deposit.0 < storage_cost {
+            self.airdrops.pop();
+            log!(
+                "ERR: the attached deposit {} does not cover the storage cost {}",
+                storage_deposit.0,
+                storage_cost
+            );
+            Promise::new(storage_payer_id).transfer(storage_deposit.0);
+            return None;
+        }
+        if storage_deposit.0 > storage_cost {
+            Promise::new(storage_payer_id).transfer(storage_deposit.0 - storage_cost);
+        }
+        let airdrop_index = self.airdrops.len() - 1;
+        let airdrop = self.airdrops[airdrop_index].live_mut();
+        airdrop.storage_deposit = storage_cost;
+        events::Event::AirdropRegistered(vec![events::AirdropRegisteredData {
+            airdrop_index: airdrop_index as u16,
+            title: airdrop.title.clone(),
+            token_contract: airdrop.token_contract.clone(),
+            manager_id: airdrop.manager_id.clone(),
+            sponsor_id: airdrop.sponsor_id.clone(),
+            start_ms: start_timestamp_ms,
+            end_ms: end_timestamp_ms,
+        }])
+        .emit();
+
+        Some(airdrop_index as u16)
     }
 
-    // create claims for an inactive airdrop
+    // create claims for an airdrop, or top-up existing user claims
+    // if the airdrop is no longer a draft, the contract balance must cover the new claims
+    // for sponsored airdrops, the sponsor deposit must cover them
+    // the attached NEAR pays the storage of the new claims, the excess is refunded
     #[payable]
     pub fn add_claims(
         &mut self,
         airdrop_index: u16,
         total_amount: U128,
         data: Vec<(String, String)>,
+    ) -> PromiseOrValue<()> {
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::ClaimUploader);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        if airdrop.sponsor_id.is_none() && airdrop.status != airdrop::AirdropStatus::Draft {
+            // the airdrop was funded, check the new total is also funded
+            // if the claims are not added, after_add_claims refunds the attached deposit
+            let storage_payer_id = env::predecessor_account_id();
+            let storage_deposit = U128::from(env::attached_deposit());
+            return ext_ft_core::ext(airdrop.token_contract.clone())
+                .with_static_gas(GAS_FOR_FT_BALANCE_OF)
+                .with_unused_gas_weight(0)
+                .ft_balance_of(env::current_account_id())
+                .then(
+                    ext_self::ext(env::current_account_id()).add_claims_step_2(
+                        airdrop_index,
+                        total_amount,
+                        data,
+                        storage_payer_id.clone(),
+                        storage_deposit,
+                    ),
+                )
+                .then(
+                    ext_self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_AFTER_ADD_CLAIMS)
+                        .with_unused_gas_weight(0)
+                        .after_add_claims(storage_payer_id, storage_deposit),
+                )
+                .into();
+        }
+        let storage_payer_id = env::predecessor_account_id();
+        let storage_before = env::storage_usage();
+        self.internal_create_claims(airdrop_index, total_amount.0, data, &storage_payer_id);
+        self.internal_pay_storage(storage_before, env::attached_deposit(), &storage_payer_id);
+        PromiseOrValue::Value(())
+    }
+    // after obtaining the contract balance, for funded airdrops
+    // returns false if the claims were not added
+    #[private]
+    pub fn add_claims_step_2(
+        &mut self,
+        airdrop_index: u16,
+        total_amount: U128,
+        data: Vec<(String, String)>,
+        storage_payer_id: AccountId,
+        storage_deposit: U128,
+        #[callback_result] contract_balance: Result<U128, PromiseError>,
+    ) -> bool {
+        let token_contract = self.airdrops[airdrop_index as u32].live().token_contract.clone();
+        let contract_balance = match contract_balance {
+            Ok(contract_balance) => contract_balance,
+            Err(_) => {
+                log!("ERR: can not get the balance of {}", token_contract);
+                return false;
+            }
+        };
+        let committed_this_token = self.internal_committed_tokens(&token_contract) + total_amount.0;
+        if contract_balance.0 < committed_this_token {
+            log!(
+                "ERR: for token:{} contract_balance {} < committed {}",
+                token_contract,
+                contract_balance.0,
+                committed_this_token
+            );
+            return false;
+        }
+
+        let storage_before = env::storage_usage();
+        self.internal_create_claims(airdrop_index, total_amount.0, data, &storage_payer_id);
+        self.internal_pay_storage(storage_before, storage_deposit.0, &storage_payer_id);
+        log!(
+            "Added {} to funded airdrop index {}, committed {} for {}",
+            total_amount.0,
+            airdrop_index,
+            committed_this_token,
+            token_contract
+        );
+        true
+    }
+    // refunds the whole storage deposit if add_claims_step_2 failed or did not add the claims
+    #[private]
+    pub fn after_add_claims(
+        &mut self,
+        storage_payer_id: AccountId,
+        storage_deposit: U128,
+        #[callback_result] added: Result<bool, PromiseError>,
     ) {
+        if added == Ok(true) {
+            return;
+        }
+        log!(
+            "Claims not added, refunding {} to {}",
+            storage_deposit.0,
+            storage_payer_id
+        );
+        Promise::new(storage_payer_id).transfer(storage_deposit.0);
+    }
+
+    // fix the amount of a single user claim, only while the airdrop is a draft
+    // new_amount is a decimal string, as in add_claims data
+    #[payable]
+    pub fn update_claim(&mut self, airdrop_index: u16, account_id: AccountId, new_amount: String) {
         assert_one_yocto();
-        self.assert_operator();
-        self.internal_create_claims(airdrop_index as u16, total_amount.0, data);
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::ClaimUploader);
+        self.assert_not_paused();
+        let amount = parse_token_amount(
+            &new_amount,
+            self.airdrops[airdrop_index as u32].live().token_decimals,
+        );
+        self.internal_update_claim(airdrop_index, &account_id, Some(amount));
     }
 
-    // after enough tokens have been transferred to the contract
+    // compliance hold on a user: one airdrop, or all of them if airdrop_index is None
+    // the rest of the airdrop continues
+    #[payable]
+    pub fn freeze_user_claim(
+        &mut self,
+        account_id: AccountId,
+        airdrop_index: Option<u16>,
+        reason: String,
+    ) {
+        assert_one_yocto();
+        match airdrop_index {
+            Some(index) => self.assert_airdrop_manager_or_role(index, Role::AirdropAdmin),
+            None => self.assert_role(Role::AirdropAdmin),
+        }
+        self.assert_not_paused();
+        assert!(
+            reason.len() <= MAX_FREEZE_REASON_LEN,
+            "The reason can have at most {} bytes",
+            MAX_FREEZE_REASON_LEN
+        );
+        self.internal_set_claims_frozen(&account_id, airdrop_index, Some(reason));
+    }
     #[payable]
-    pub fn enable_airdrop(&mut self, airdrop_index: u16) -> Promise {
+    pub fn unfreeze_user_claim(&mut self, account_id: AccountId, airdrop_index: Option<u16>) {
         assert_one_yocto();
-        self.assert_operator();
-        let token_contract = self.airdrops[airdrop_index as usize].token_contract.clone();
+        match airdrop_index {
+            Some(index) => self.assert_airdrop_manager_or_role(index, Role::AirdropAdmin),
+            None => self.assert_role(Role::AirdropAdmin),
+        }
+        self.assert_not_paused();
+        self.internal_set_claims_frozen(&account_id, airdrop_index, None);
+    }
+
+    // remove a single user claim, only while the airdrop is a draft
+    #[payable]
+    pub fn remove_claim(&mut self, airdrop_index: u16, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::ClaimUploader);
+        self.assert_not_paused();
+        // the freed storage goes back to the payer of the claim
+        self.internal_update_claim(airdrop_index, &account_id, None);
+    }
+
+    // after enough tokens have been transferred to the contract, moves a Draft airdrop to Funded
+    // its claims can no longer be updated or removed, and new claims need a new balance check
+    #[payable]
+    pub fn confirm_funding(&mut self, airdrop_index: u16) -> PromiseOrValue<()> {
+        assert_one_yocto();
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        assert!(
+            airdrop.status == airdrop::AirdropStatus::Draft,
+            "Airdrop {} is not a draft",
+            airdrop_index
+        );
+        assert!(
+            airdrop.total_distributed > 0,
+            "ERR: airdrop {} has no claims",
+            airdrop_index
+        );
+        if airdrop.sponsor_id.is_some() {
+            // the sponsor deposit covers the claims, checked in add_claims
+            airdrop.change_status(airdrop::AirdropStatus::Funded);
+            events::Event::AirdropFunded(vec![events::AirdropData { airdrop_index }]).emit();
+            return PromiseOrValue::Value(());
+        }
+        // get this contract's balance in the token
+        // to ensure that the contract has enough tokens to distribute
+        ext_ft_core::ext(airdrop.token_contract.clone())
+            .ft_balance_of(env::current_account_id())
+            .then(ext_self::ext(env::current_account_id()).confirm_funding_step_2(airdrop_index))
+            .into()
+    }
+    #[private]
+    pub fn confirm_funding_step_2(
+        &mut self,
+        airdrop_index: u16,
+        #[callback] contract_balance: U128,
+    ) {
+        let token_contract = self.airdrops[airdrop_index as u32].live().token_contract.clone();
+        let committed_this_token = self.internal_committed_tokens(&token_contract);
+        assert!(
+            contract_balance.0 >= committed_this_token,
+            "ERR: for token:{} contract_balance {} < committed {}",
+            token_contract,
+            contract_balance.0,
+            committed_this_token
+        );
+        self.airdrops[airdrop_index as u32]
+            .live_mut()
+            .change_status(airdrop::AirdropStatus::Funded);
+        log!(
+            "Airdrop index {} funded for {} with contract_balance {} and committed {}",
+            airdrop_index,
+            token_contract,
+            contract_balance.0,
+            committed_this_token
+        );
+        events::Event::AirdropFunded(vec![events::AirdropData { airdrop_index }]).emit();
+    }
+
+    // launch a funded airdrop, or re-enable a paused one
+    #[payable]
+    pub fn enable_airdrop(&mut self, airdrop_index: u16) -> PromiseOrValue<()> {
+        assert_one_yocto();
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        assert!(
+            airdrop.status != airdrop::AirdropStatus::Draft,
+            "Airdrop {} is not funded yet, call confirm_funding first",
+            airdrop_index
+        );
+        if airdrop.sponsor_id.is_some() {
+            // the sponsor deposit covers the claims, checked in add_claims
+            assert!(
+                airdrop.total_distributed > 0,
+                "ERR: airdrop {} has no claims",
+                airdrop_index
+            );
+            airdrop.change_status(airdrop::AirdropStatus::Active);
+            log!(
+                "Sponsored airdrop index {} enabled with total_distributed {}",
+                airdrop_index,
+                airdrop.total_distributed
+            );
+            events::Event::AirdropEnabled(vec![events::AirdropData { airdrop_index }]).emit();
+            return PromiseOrValue::Value(());
+        }
+        let token_contract = airdrop.token_contract.clone();
         // get this contract's balance in the token
         // to ensure that the contract has enough tokens to distribute
         ext_ft_core::ext(token_contract)
             .ft_balance_of(env::current_account_id())
             .then(ext_self::ext(env::current_account_id()).enable_airdrop_step_2(airdrop_index))
+            .into()
     }
     #[private]
     pub fn enable_airdrop_step_2(
@@ -170,7 +685,7 @@ impl GradualReleaseContract {
         airdrop_index: u16,
         #[callback] contract_balance: U128,
     ) {
-        let airdrop = &mut self.airdrops[airdrop_index as usize];
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
         let token_contract = airdrop.token_contract.clone();
         assert!(
             contract_balance.0 > 0,
@@ -179,7 +694,7 @@ impl GradualReleaseContract {
             token_contract
         );
 
-        airdrop.change_status(airdrop::status_code::ENABLED);
+        airdrop.change_status(airdrop::AirdropStatus::Active);
 
         let total_in_claims_this_token = self
             .total_in_claims_per_token
@@ -190,79 +705,315 @@ impl GradualReleaseContract {
             "ERR: for token {}, total_in_claims is 0",
             token_contract,
         );
+        let committed_this_token = self.internal_committed_tokens(&token_contract);
         assert!(
-            contract_balance.0 >= total_in_claims_this_token,
-            "ERR: for token:{} contract_balance {} < total_in_claims {}",
+            contract_balance.0 >= committed_this_token,
+            "ERR: for token:{} contract_balance {} < committed {}",
             token_contract,
             contract_balance.0,
-            total_in_claims_this_token
+            committed_this_token
         );
 
         log!(
-            "Airdrop index {} enabled for {} with contract_balance {} and total_in_claims {}",
+            "Airdrop index {} enabled for {} with contract_balance {} and committed {}",
             airdrop_index,
             token_contract,
             contract_balance.0,
-            total_in_claims_this_token
+            committed_this_token
         );
+        events::Event::AirdropEnabled(vec![events::AirdropData { airdrop_index }]).emit();
     }
 
-    // archive an airdrop, no longer appears in get_airdrops()
-    pub fn archive_airdrop(&mut self, airdrop_index: u16) {
-        self.assert_operator();
-        self.airdrops[airdrop_index as usize].change_status(airdrop::status_code::ARCHIVED);
+    // change who can manage an airdrop
+    #[payable]
+    pub fn set_airdrop_manager(&mut self, airdrop_index: u16, manager_id: AccountId) {
+        assert_one_yocto();
+        self.assert_role(Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        log!(
+            "{} changed manager of airdrop_index:{} from {} to {}",
+            env::predecessor_account_id(),
+            airdrop_index,
+            airdrop.manager_id,
+            manager_id
+        );
+        events::Event::AirdropManagerChanged(vec![events::AirdropManagerChangedData {
+            airdrop_index,
+            old_manager_id: airdrop.manager_id.clone(),
+            new_manager_id: manager_id.clone(),
+        }])
+        .emit();
+        airdrop.manager_id = manager_id;
     }
 
-    // disable an airdrop, can be enabled later
-    pub fn disable_airdrop(&mut self, airdrop_index: u16) {
-        self.assert_operator();
-        self.airdrops[airdrop_index as usize].change_status(airdrop::status_code::DISABLED);
+    // archive an airdrop (Ended), no longer appears in get_airdrops()
+    // immediate once its release has ended. Before that the archive is queued
+    // and runs with execute_action after timelock_delay_ms; returns the action id.
+    #[payable]
+    pub fn archive_airdrop(&mut self, airdrop_index: u16) -> Option<u64> {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        assert!(
+            airdrop.status.can_change_to(airdrop::AirdropStatus::Ended),
+            "ERR: Airdrop status can not change from {:?} to Ended",
+            airdrop.status
+        );
+        if get_current_epoch_millis() < airdrop.effective_schedule().end_ms {
+            return Some(
+                self.internal_propose_action(timelock::AdminAction::ArchiveAirdrop {
+                    airdrop_index,
+                }),
+            );
+        }
+        airdrop.change_status(airdrop::AirdropStatus::Ended);
+        events::Event::AirdropArchived(vec![events::AirdropData { airdrop_index }]).emit();
+        refund_attached_deposit();
+        None
+    }
+
+    // cancel an airdrop registered by mistake. It must not be launched yet (Draft or Funded).
+    // Undoes the per-token totals and returns the funded tokens to refund_to.
+    // Nothing changes until the contract balance is known, so a failed balance call can be retried
+    // The user claims are removed later in batches with remove_cancelled_claims.
+    // A sponsored airdrop can also be cancelled by its sponsor, and is always refunded to the sponsor
+    #[payable]
+    pub fn cancel_airdrop(&mut self, airdrop_index: u16, refund_to: AccountId) -> PromiseOrValue<()> {
+        assert_one_yocto();
+        let sponsor_id = self.airdrops[airdrop_index as u32].live().sponsor_id.clone();
+        match &sponsor_id {
+            Some(sponsor_id) => {
+                require!(
+                    env::predecessor_account_id() == *sponsor_id
+                        || self.has_role(&env::predecessor_account_id(), Role::Treasurer),
+                    "Only the sponsor or a Treasurer can cancel a sponsored airdrop"
+                );
+                require!(
+                    refund_to == *sponsor_id,
+                    "A sponsored airdrop is refunded to its sponsor"
+                );
+            }
+            None => self.assert_role(Role::Treasurer),
+        }
+        self.assert_not_paused();
+        self.assert_can_cancel(airdrop_index);
+
+        if sponsor_id.is_some() {
+            self.internal_cancel_airdrop(airdrop_index);
+            let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+            // the claims go back to the sponsor deposit, then the whole deposit is refunded
+            add_to_token_total(
+                &mut self.unallocated_sponsored_per_token,
+                &airdrop.token_contract,
+                airdrop.total_distributed,
+            );
+            airdrop.total_distributed = 0;
+            return self.internal_refund_sponsor(airdrop_index);
+        }
+
+        // get this contract's balance in the token, to compute the refund
+        let token_contract = self.airdrops[airdrop_index as u32].live().token_contract.clone();
+        ext_ft_core::ext(token_contract)
+            .ft_balance_of(env::current_account_id())
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .cancel_airdrop_step_2(airdrop_index, refund_to),
+            )
+            .into()
+    }
+    #[private]
+    pub fn cancel_airdrop_step_2(
+        &mut self,
+        airdrop_index: u16,
+        refund_to: AccountId,
+        #[callback_result] contract_balance: Result<U128, PromiseError>,
+    ) -> PromiseOrValue<()> {
+        let contract_balance = match contract_balance {
+            Ok(contract_balance) => contract_balance,
+            Err(_) => {
+                log!(
+                    "ERR: can not get the balance, airdrop {} was not cancelled",
+                    airdrop_index
+                );
+                return PromiseOrValue::Value(());
+            }
+        };
+        // checked again, the airdrop could have changed since cancel_airdrop
+        self.internal_cancel_airdrop(airdrop_index);
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        let committed_this_token = self.internal_committed_tokens(&airdrop.token_contract);
+        // only what is not needed by other airdrops of the same token can be returned
+        let refund = std::cmp::min(
+            airdrop.total_distributed,
+            contract_balance.0.saturating_sub(committed_this_token),
+        );
+        log!(
+            "Airdrop index {} cancelled, refunding {} {} to {}",
+            airdrop_index,
+            refund,
+            airdrop.token_symbol,
+            refund_to
+        );
+        if refund == 0 {
+            return PromiseOrValue::Value(());
+        }
+        self.internal_refund_cancelled(airdrop_index, &refund_to, refund)
+            .into()
+    }
+
+    // send again the refund of a cancelled airdrop, after a failed transfer
+    #[payable]
+    pub fn retry_cancel_refund(&mut self, airdrop_index: u16) -> Promise {
+        assert_one_yocto();
+        self.assert_role(Role::Treasurer);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        let (refund_to, amount) = match &airdrop.cancel_refund {
+            Some(refund) if refund.failed => (refund.refund_to.clone(), refund.amount),
+            _ => panic!("Airdrop {} has no failed cancel refund", airdrop_index),
+        };
+        // committed again by internal_refund_cancelled
+        sub_from_token_total(
+            &mut self.cancel_refunds_per_token,
+            &airdrop.token_contract,
+            amount,
+        );
+        self.internal_refund_cancelled(airdrop_index, &refund_to, amount)
+    }
+
+    // remove the user claims of a cancelled airdrop, scanning up to `limit` users from `from_index`
+    // returns the from_index for the next call, the scan is complete when it reaches get_contract_info().user_count
+    pub fn remove_cancelled_claims(&mut self, airdrop_index: u16, from_index: u32, limit: u32) -> u32 {
+        // a closed airdrop that was launched keeps its unclaimed claims in the totals
+        assert!(
+            self.airdrops[airdrop_index as u32].live().was_cancelled(),
+            "Airdrop {} was not cancelled",
+            airdrop_index
+        );
+        require!(
+            self.legacy_claims.is_empty(),
+            "Claims not migrated yet, call migrate_claims first"
+        );
+        let mut freed = FreedStorage::new();
+        let mut removed = vec![];
+        let mut index = from_index as u64;
+        for _ in 0..limit {
+            let keys = self.user_airdrops.keys_as_vector();
+            if index >= keys.len() {
+                break;
+            }
+            let account_id = keys.get(index).unwrap();
+            let airdrop_indexes = self.internal_get_user_airdrops(&account_id);
+            if !airdrop_indexes.contains(&airdrop_index) {
+                index += 1;
+                continue;
+            }
+            removed.extend(self.internal_remove_claims(&account_id, &[airdrop_index], &mut freed));
+            // when it was the user's last claim, the last user takes this position, so do not advance
+            if airdrop_indexes.len() > 1 {
+                index += 1;
+            }
+        }
+        self.internal_refund_storage(freed);
+        events::Event::emit_claims_removed(&removed);
+        index as u32
+    }
+
+    // close an ended airdrop. The remainder of a sponsored airdrop is returned to the sponsor
+    pub fn close_airdrop(&mut self, airdrop_index: u16) -> PromiseOrValue<()> {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        airdrop.change_status(airdrop::AirdropStatus::Closed);
+        events::Event::AirdropClosed(vec![events::AirdropData { airdrop_index }]).emit();
+        if airdrop.sponsor_id.is_some() {
+            return self.internal_refund_sponsor(airdrop_index);
+        }
+        PromiseOrValue::Value(())
+    }
+
+    // pause an active airdrop, can be enabled later
+    // queued, runs with execute_action after timelock_delay_ms. Returns the action id
+    // with freeze_accrual the vesting clock also stops until the airdrop is enabled again
+    // queued actions are paid by the attached deposit, see internal_propose_action
+    #[payable]
+    pub fn disable_airdrop(&mut self, airdrop_index: u16, freeze_accrual: Option<bool>) -> u64 {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        let status = self.airdrops[airdrop_index as u32].live().status;
+        assert!(
+            status.can_change_to(airdrop::AirdropStatus::Paused),
+            "ERR: Airdrop status can not change from {:?} to Paused",
+            status
+        );
+        self.internal_propose_action(timelock::AdminAction::DisableAirdrop {
+            airdrop_index,
+            freeze_accrual: freeze_accrual.unwrap_or(false),
+        })
     }
 
     // ------------------------
     // change airdrop schedule
     // ------------------------
+    // immediate before launch. For a launched airdrop the change is queued
+    // and runs with execute_action after timelock_delay_ms; returns the action id.
+    // The new schedule can not release less than the current one, unless the owner sets force
+    #[payable]
     pub fn change_schedule(
         &mut self,
         airdrop_index: u16,
         start_timestamp_ms: U64,
         end_timestamp_ms: U64,
-    ) {
-        self.assert_operator();
+        force: Option<bool>,
+    ) -> Option<u64> {
+        let force = force.unwrap_or(false);
+        if force {
+            self.assert_role(Role::Owner);
+        } else {
+            self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        }
+        self.assert_not_paused();
+        if !self.airdrops[airdrop_index as u32].live().is_launched() {
+            self.internal_change_schedule(
+                airdrop_index,
+                start_timestamp_ms,
+                end_timestamp_ms,
+                force,
+            );
+            refund_attached_deposit();
+            return None;
+        }
         assert!(
             start_timestamp_ms.0 <= end_timestamp_ms.0,
             "Start timestamp_ms must be before end timestamp_ms"
         );
-        self.airdrops[airdrop_index as usize].release_schedule = airdrop::TimestampPeriod {
-            start_ms: start_timestamp_ms.0,
-            end_ms: end_timestamp_ms.0,
-        };
+        // fail early, checked again on execution
+        self.assert_schedule_not_behind(
+            airdrop_index,
+            &airdrop::TimestampPeriod {
+                start_ms: start_timestamp_ms.0,
+                end_ms: end_timestamp_ms.0,
+            },
+            force,
+        );
+        Some(
+            self.internal_propose_action(timelock::AdminAction::ChangeSchedule {
+                airdrop_index,
+                start_timestamp_ms,
+                end_timestamp_ms,
+                force,
+            }),
+        )
     }
 
     // ------------------------------------
     // user claims tokens
     // ------------------------------------
     pub fn claim(&mut self, airdrop_index: u16) -> Promise {
+        self.assert_not_paused();
         self.internal_claim(airdrop_index, &env::predecessor_account_id())
     }
-
-    // ------------------------------------
-    // cleanup function, remove used claims
-    // ------------------------------------
-    pub fn remove_used_claims(&mut self, accounts: Vec<AccountId>) {
-        for account_id in accounts {
-            let user_claims_maybe = &mut self.available_claims.get(&account_id);
-            if let Some(user_claims) = user_claims_maybe {
-                user_claims.retain(|claim| claim.assigned_tokens > claim.claimed_tokens);
-                // save
-                if user_claims.is_empty() {
-                    self.available_claims.remove(&account_id);
-                } else {
-                    self.available_claims.insert(&account_id, &user_claims);
-                }
-            }
-        }
-    }
 }
 
 // #[cfg(not(target_arch = "wasm32"))]
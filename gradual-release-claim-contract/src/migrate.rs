@@ -1,25 +1,161 @@
-// use crate::*;
-// use near_sdk::{env, near_bindgen};
+use crate::*;
+use near_sdk::{env, near_bindgen};
+
+/// Layout of the contract state
+/// - 2: v2.0.0, did not store its version
+/// - 3: claims keyed by (account, airdrop), airdrops in a lazily loaded Vector
+///
+/// When the layout changes: bump STATE_VERSION, keep the previous layout here as StateV{n},
+/// and add its arm to migrate()
+pub const STATE_VERSION: u16 = 3;
+
+// stored under its own key, so migrate can read it before knowing the layout
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+// v2.0.0 layout: airdrops in a Vec inside the root state, one Vec of claims per user
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TimestampPeriodV2 {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
 
-/*
 #[derive(BorshDeserialize, BorshSerialize)]
-pub struct OldState {
+pub struct AirdropV2 {
+    pub status_code: u8, // 0 DISABLED, 1 ENABLED, 2 ARCHIVED
+    pub title: String,
+    pub token_contract: AccountId,
+    pub token_symbol: String,
+    pub token_decimals: u8,
+    pub release_schedule: TimestampPeriodV2,
+    pub total_distributed: u128,
+    pub total_claimed: u128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StateV2 {
     pub owner_id: AccountId,
     pub operator_id: AccountId,
+    pub airdrops: Vec<AirdropV2>,
+    pub available_claims: UnorderedMap<AccountId, Vec<claims::LegacyUserClaimInfo>>,
+    pub total_in_claims_per_token: UnorderedMap<Token, u128>,
+}
+
+impl AirdropV2 {
+    // a disabled airdrop that never had claims is still a draft, otherwise it was paused
+    fn status(&self) -> airdrop::AirdropStatus {
+        match self.status_code {
+            0 if self.total_claimed == 0 => airdrop::AirdropStatus::Draft,
+            0 => airdrop::AirdropStatus::Paused,
+            1 => airdrop::AirdropStatus::Active,
+            2 => airdrop::AirdropStatus::Ended,
+            code => panic!("Unknown airdrop status code {}", code),
+        }
+    }
+}
+
+pub(crate) fn read_state_version() -> u16 {
+    match env::storage_read(STATE_VERSION_KEY) {
+        Some(bytes) => u16::try_from_slice(&bytes).expect("Invalid state version"),
+        None => 2,
+    }
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.to_le_bytes());
 }
 
 #[near_bindgen]
-impl MetaVoteContract {
+impl GradualReleaseContract {
+    // upgrades the state from any known layout, call it on every redeploy
     #[init(ignore_state)]
     #[private] // only contract account can call this fn
     pub fn migrate() -> Self {
+        let from_version = read_state_version();
+        log!(
+            "Migrating state from version {} to {}",
+            from_version,
+            STATE_VERSION
+        );
+        let contract = match from_version {
+            2 => Self::migrate_from_v2(),
+            // redeploy without layout changes
+            STATE_VERSION => env::state_read().expect("failed"),
+            version => panic!("Unknown state version {}", version),
+        };
+        write_state_version();
+        // a successful upgrade, the staged code is no longer needed
+        upgrade::internal_remove_staged_code();
+        contract
+    }
+}
+
+impl GradualReleaseContract {
+    fn migrate_from_v2() -> Self {
         // retrieve the current state from the contract
-        let old: OldState = env::state_read().expect("failed");
+        let old: StateV2 = env::state_read().expect("failed");
+        let mut airdrops = Vector::new(StorageKey::Airdrops);
+        for old_airdrop in old.airdrops {
+            let status = old_airdrop.status();
+            airdrops.push(airdrop::AirdropEntry::Live(airdrop::Airdrop {
+                status,
+                title: old_airdrop.title,
+                token_contract: old_airdrop.token_contract,
+                manager_id: old.operator_id.clone(),
+                sponsor_id: None,
+                deposited_tokens: 0,
+                token_symbol: old_airdrop.token_symbol,
+                token_decimals: old_airdrop.token_decimals,
+                release_schedule: airdrop::TimestampPeriod {
+                    start_ms: old_airdrop.release_schedule.start_ms,
+                    end_ms: old_airdrop.release_schedule.end_ms,
+                },
+                frozen_since_ms: None,
+                total_frozen_ms: 0,
+                allowlist_required: false,
+                total_distributed: old_airdrop.total_distributed,
+                total_claimed: old_airdrop.total_claimed,
+                total_reclaimed: 0,
+                pending_sponsor_refund: 0,
+                cancel_refund: None,
+                // counted when the claims are moved to the new layout
+                claim_count: 0,
+                // the history starts at the migration
+                status_history: vec![airdrop::StatusChange::now(status)],
+                // the history does not tell, v2.0.0 had no cancel
+                cancelled: false,
+                // the contract paid this storage, nothing to refund
+                storage_payer_id: env::current_account_id(),
+                storage_deposit: 0,
+            }));
+        }
+        log!("Migrated {} airdrops", airdrops.len());
         // return the new state
         Self {
             owner_id: old.owner_id,
             operator_id: old.operator_id,
+            pending_owner_id: None,
+            pending_operator_id: None,
+            operator_accept_after_ms: 0,
+            roles: UnorderedSet::new(StorageKey::Roles),
+            airdrops,
+            paused: false,
+            claims: LookupMap::new(StorageKey::Claims),
+            user_airdrops: UnorderedMap::new(StorageKey::UserAirdrops),
+            // moved to the new layout with migrate_claims
+            legacy_claims: old.available_claims,
+            total_in_claims_per_token: old.total_in_claims_per_token,
+            platform_fee_bp: 0,
+            unallocated_sponsored_per_token: UnorderedMap::new(StorageKey::UnallocatedSponsored),
+            platform_fees_per_token: UnorderedMap::new(StorageKey::PlatformFees),
+            cancel_refunds_per_token: UnorderedMap::new(StorageKey::CancelRefunds),
+            timelock_delay_ms: DEFAULT_TIMELOCK_DELAY_MS,
+            next_action_id: 0,
+            pending_actions: UnorderedMap::new(StorageKey::PendingActions),
+            pending_action_slots: LookupMap::new(StorageKey::PendingActionSlots),
+            denylist: UnorderedMap::new(StorageKey::Denylist),
+            allowlist: LookupMap::new(StorageKey::Allowlist),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            storage_treasury_id: None,
         }
     }
 }
-*/
\ No newline at end of file
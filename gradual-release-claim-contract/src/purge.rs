@@ -0,0 +1,154 @@
+use crate::view::PurgeProgressJSON;
+use crate::*;
+
+// A closed airdrop that no user can still claim from is purged: its remaining claims are removed
+// in batches, then the airdrop is replaced by a tombstone with its final totals.
+// The unclaimed tokens of a sponsored airdrop are refunded to the sponsor before the tombstone.
+// The tombstone keeps the airdrop index, so the later airdrops keep theirs.
+#[near_bindgen]
+impl GradualReleaseContract {
+    // removes the claims of the airdrop for up to `limit` users from `from_index`,
+    // and purges the airdrop when no claims are left. A sponsored airdrop first refunds
+    // the reclaimed tokens to the sponsor, and is purged by the next call once the refund resolved.
+    // Call it again with the returned next_index until `purged` is true,
+    // from 0 again if next_index reaches user_count with claims left (users removed meanwhile move the others)
+    pub fn purge_airdrop(
+        &mut self,
+        airdrop_index: u16,
+        from_index: u32,
+        limit: u32,
+    ) -> PurgeProgressJSON {
+        self.assert_airdrop_manager_or_role(airdrop_index, Role::AirdropAdmin);
+        self.assert_not_paused();
+        assert!(
+            self.airdrops[airdrop_index as u32]
+                .live()
+                .can_purge(get_current_epoch_millis()),
+            "Airdrop {} can not be purged, it must be closed and cancelled, fully claimed or past its release end",
+            airdrop_index
+        );
+        require!(
+            self.legacy_claims.is_empty(),
+            "Claims not migrated yet, call migrate_claims first"
+        );
+        let mut freed = FreedStorage::new();
+        let mut removed = vec![];
+        let mut index = from_index as u64;
+        for _ in 0..limit {
+            let keys = self.user_airdrops.keys_as_vector();
+            if index >= keys.len() || self.airdrops[airdrop_index as u32].live().claim_count == 0 {
+                break;
+            }
+            let account_id = keys.get(index).unwrap();
+            let airdrop_indexes = self.internal_get_user_airdrops(&account_id);
+            if !airdrop_indexes.contains(&airdrop_index) {
+                index += 1;
+                continue;
+            }
+            self.internal_remove_unclaimed(&account_id, airdrop_index);
+            removed.extend(self.internal_remove_claims(&account_id, &[airdrop_index], &mut freed));
+            // when it was the user's last claim, the last user takes this position, so do not advance
+            if airdrop_indexes.len() > 1 {
+                index += 1;
+            }
+        }
+        self.internal_refund_storage(freed);
+        events::Event::emit_claims_removed(&removed);
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        let claims_left = airdrop.claim_count;
+        let mut purged = false;
+        if claims_left == 0 {
+            if airdrop.pending_sponsor_refund > 0 {
+                log!(
+                    "Airdrop {} waits for the sponsor refund of {}",
+                    airdrop_index,
+                    airdrop.pending_sponsor_refund
+                );
+            } else if let Some(refund) = &airdrop.cancel_refund {
+                log!(
+                    "Airdrop {} waits for the cancel refund of {} to {}",
+                    airdrop_index,
+                    refund.amount,
+                    refund.refund_to
+                );
+            } else if airdrop.sponsor_id.is_some() && airdrop.unallocated_tokens() > 0 {
+                // detached, on failure the next call retries it
+                let _ = self.internal_refund_sponsor(airdrop_index);
+            } else {
+                self.internal_replace_with_tombstone(airdrop_index);
+                purged = true;
+            }
+        }
+        PurgeProgressJSON {
+            next_index: index as u32,
+            user_count: self.user_airdrops.len(),
+            claims_left,
+            purged,
+        }
+    }
+}
+
+impl GradualReleaseContract {
+    // the tokens left in a claim removed past the release end are no longer owed,
+    // those of a sponsored airdrop go back to its unallocated tokens, refunded to the sponsor
+    fn internal_remove_unclaimed(&mut self, account_id: &AccountId, airdrop_index: u16) {
+        let claim = self.internal_get_claim_or_panic(account_id, airdrop_index);
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        if airdrop.was_cancelled() {
+            return;
+        }
+        let unclaimed = claim.assigned_tokens - claim.claimed_tokens;
+        if unclaimed > 0 {
+            airdrop.total_reclaimed += unclaimed;
+            if airdrop.sponsor_id.is_some() {
+                add_to_token_total(
+                    &mut self.unallocated_sponsored_per_token,
+                    &airdrop.token_contract,
+                    unclaimed,
+                );
+            }
+            let current_amount = self
+                .total_in_claims_per_token
+                .get(&airdrop.token_contract)
+                .unwrap_or(0);
+            self.total_in_claims_per_token
+                .insert(&airdrop.token_contract, &(current_amount - unclaimed));
+        }
+    }
+
+    // the storage freed by the tombstone goes back to the storage payer, up to what is left of its ledger.
+    // Anyone can register a sponsored airdrop, so its storage is kept: registering, cancelling and purging
+    // airdrops to use up the indexes is not free
+    fn internal_replace_with_tombstone(&mut self, airdrop_index: u16) {
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        let storage_payer_id = airdrop.storage_payer_id.clone();
+        let storage_deposit = if airdrop.sponsor_id.is_some() {
+            0
+        } else {
+            airdrop.storage_deposit
+        };
+        let tombstone = airdrop::PurgedAirdrop {
+            token_contract: airdrop.token_contract.clone(),
+            total_distributed: airdrop.total_distributed,
+            total_claimed: airdrop.total_claimed,
+            purged_at_ms: get_current_epoch_millis(),
+        };
+        // write pending changes first, to measure only the replacement
+        self.airdrops.flush();
+        let storage_before = env::storage_usage();
+        self.airdrops[airdrop_index as u32] = airdrop::AirdropEntry::Purged(tombstone);
+        self.airdrops.flush();
+        let freed_bytes = storage_before.saturating_sub(env::storage_usage());
+        let refund = std::cmp::min(
+            freed_bytes as u128 * env::storage_byte_cost(),
+            storage_deposit,
+        );
+        self.internal_send_freed_storage(Some(&storage_payer_id), refund, freed_bytes);
+        log!(
+            "{} purged airdrop {}",
+            env::predecessor_account_id(),
+            airdrop_index
+        );
+        events::Event::AirdropPurged(vec![events::AirdropData { airdrop_index }]).emit();
+    }
+}
@@ -0,0 +1,38 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    serde::{Deserialize, Serialize},
+};
+
+/// Each operator method requires one of these roles.
+///
+/// - Owner: grants and revokes roles, proposes a new owner or operator, unpauses the contract
+/// - AirdropAdmin: registers airdrops, enables, disables and archives them, changes schedules
+/// - ClaimUploader: adds, updates and removes user claims
+/// - Pauser: pauses the contract
+/// - Treasurer: moves tokens out of the contract (refunds, platform fees)
+///
+/// `owner_id` implicitly holds Owner and Pauser, `operator_id` implicitly holds
+/// AirdropAdmin, ClaimUploader and Treasurer. Other accounts get roles with `grant_role`
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Owner,
+    AirdropAdmin,
+    ClaimUploader,
+    Pauser,
+    Treasurer,
+}
+
+pub const ALL_ROLES: [Role; 5] = [
+    Role::Owner,
+    Role::AirdropAdmin,
+    Role::ClaimUploader,
+    Role::Pauser,
+    Role::Treasurer,
+];
+
+pub const OWNER_IMPLICIT_ROLES: [Role; 2] = [Role::Owner, Role::Pauser];
+pub const OPERATOR_IMPLICIT_ROLES: [Role; 3] =
+    [Role::AirdropAdmin, Role::ClaimUploader, Role::Treasurer];
@@ -0,0 +1,198 @@
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::PromiseResult;
+
+use crate::internal::ext_self as ext_internal;
+use crate::*;
+
+// Sponsored airdrops: anyone can register an airdrop, fund it with ft_transfer_call
+// and upload its claims. The platform keeps platform_fee_bp of every deposit.
+#[near_bindgen]
+impl GradualReleaseContract {
+    // the caller becomes the sponsor and manager of the airdrop
+    // the attached NEAR pays the airdrop storage, the excess is refunded
+    // returns airdrop index, or None if the registration failed and the deposit was refunded
+    #[payable]
+    pub fn register_sponsored_airdrop(
+        &mut self,
+        title: String,
+        token_contract: AccountId,
+        start_timestamp_ms: U64,
+        end_timestamp_ms: U64,
+    ) -> Promise {
+        self.assert_not_paused();
+        self.internal_register_airdrop(airdrop::AirdropRegistration {
+            title,
+            token_contract,
+            start_timestamp_ms,
+            end_timestamp_ms,
+            manager_id: env::predecessor_account_id(),
+            sponsored: true,
+            storage_payer_id: env::predecessor_account_id(),
+            storage_deposit: U128::from(env::attached_deposit()),
+        })
+    }
+
+    // basis points of every sponsor deposit kept by the platform
+    pub fn set_platform_fee_bp(&mut self, platform_fee_bp: u16) {
+        self.assert_role(Role::Owner);
+        assert!(
+            platform_fee_bp <= MAX_PLATFORM_FEE_BP,
+            "The platform fee can not be higher than {} bp",
+            MAX_PLATFORM_FEE_BP
+        );
+        events::Event::PlatformFeeChanged(vec![events::PlatformFeeChangedData {
+            old_fee_bp: self.platform_fee_bp,
+            new_fee_bp: platform_fee_bp,
+        }])
+        .emit();
+        self.platform_fee_bp = platform_fee_bp;
+    }
+
+    #[payable]
+    pub fn withdraw_platform_fees(
+        &mut self,
+        token_contract: AccountId,
+        receiver_id: AccountId,
+    ) -> Promise {
+        assert_one_yocto();
+        self.assert_role(Role::Treasurer);
+        self.assert_not_paused();
+        let amount = self
+            .platform_fees_per_token
+            .get(&token_contract)
+            .unwrap_or(0);
+        assert!(amount > 0, "No platform fees for {}", token_contract);
+        self.platform_fees_per_token.remove(&token_contract);
+        ext_ft_core::ext(token_contract.clone())
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .with_attached_deposit(1)
+            .ft_transfer(
+                receiver_id.clone(),
+                U128::from(amount),
+                Some("platform fees".into()), // Memo
+            )
+            .then(
+                ext_internal::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_AFTER_TRANSFER)
+                    .after_withdraw_platform_fees(token_contract, receiver_id, U128::from(amount)),
+            )
+    }
+
+    #[private]
+    pub fn after_withdraw_platform_fees(
+        &mut self,
+        token_contract: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) {
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {
+                log!(
+                    "{} withdrew {} platform fees of {}",
+                    receiver_id,
+                    amount.0,
+                    token_contract
+                );
+                events::Event::PlatformFeesWithdrawn(vec![events::PlatformFeesWithdrawnData {
+                    token_contract,
+                    receiver_id,
+                    amount,
+                }])
+                .emit();
+            }
+            PromiseResult::Failed => {
+                log!(
+                    "FAIL: while withdrawing {} platform fees of {} to {}",
+                    amount.0,
+                    token_contract,
+                    receiver_id
+                );
+                // ROLLBACK
+                add_to_token_total(&mut self.platform_fees_per_token, &token_contract, amount.0);
+            }
+        };
+    }
+
+    // retry the refund of a closed sponsored airdrop, after a failed transfer
+    pub fn refund_sponsor_remainder(&mut self, airdrop_index: u16) -> PromiseOrValue<()> {
+        self.assert_not_paused();
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        let sponsor_id = airdrop.sponsor_id.clone().expect("Not a sponsored airdrop");
+        require!(
+            env::predecessor_account_id() == sponsor_id
+                || self.has_role(&env::predecessor_account_id(), Role::Treasurer),
+            "Only the sponsor or a Treasurer can refund a sponsored airdrop"
+        );
+        assert!(
+            airdrop.status == airdrop::AirdropStatus::Closed,
+            "Airdrop {} is not closed",
+            airdrop_index
+        );
+        self.internal_refund_sponsor(airdrop_index)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for GradualReleaseContract {
+    // the sponsor funds an airdrop with ft_transfer_call, msg is the airdrop index
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_paused();
+        let airdrop_index: u16 = msg.parse().expect("msg must be the airdrop index");
+        let platform_fee_bp = self.platform_fee_bp;
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        require!(
+            env::predecessor_account_id() == airdrop.token_contract,
+            "Wrong token for this airdrop"
+        );
+        require!(
+            airdrop.sponsor_id.as_ref() == Some(&sender_id),
+            "Only the sponsor can fund this airdrop"
+        );
+        assert!(
+            !matches!(
+                airdrop.status,
+                airdrop::AirdropStatus::Ended | airdrop::AirdropStatus::Closed
+            ),
+            "ERR: Airdrop {} is {:?}",
+            airdrop_index,
+            airdrop.status
+        );
+
+        let fee = proportional(amount.0, platform_fee_bp.into(), 10_000);
+        let net_amount = amount.0 - fee;
+        airdrop.deposited_tokens += net_amount;
+        add_to_token_total(
+            &mut self.unallocated_sponsored_per_token,
+            &airdrop.token_contract,
+            net_amount,
+        );
+        add_to_token_total(
+            &mut self.platform_fees_per_token,
+            &airdrop.token_contract,
+            fee,
+        );
+        log!(
+            "{} deposited {} {} to airdrop index {}, platform fee {}",
+            sender_id,
+            net_amount,
+            airdrop.token_symbol,
+            airdrop_index,
+            fee
+        );
+        events::Event::SponsorDeposited(vec![events::SponsorDepositedData {
+            airdrop_index,
+            sponsor_id: sender_id,
+            token_contract: airdrop.token_contract.clone(),
+            amount: U128::from(net_amount),
+            platform_fee: U128::from(fee),
+        }])
+        .emit();
+        PromiseOrValue::Value(U128(0))
+    }
+}
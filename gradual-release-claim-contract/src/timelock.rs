@@ -0,0 +1,284 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    serde::{Deserialize, Serialize},
+};
+
+use crate::*;
+
+/// Sensitive operations on live airdrops are queued, and can only be executed
+/// after `timelock_delay_ms`, so users can see them coming with `get_pending_actions`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AdminAction {
+    ChangeSchedule {
+        airdrop_index: u16,
+        start_timestamp_ms: U64,
+        end_timestamp_ms: U64,
+        force: bool,
+    },
+    DisableAirdrop {
+        airdrop_index: u16,
+        freeze_accrual: bool,
+    },
+    // archiving before the release end stops the claims early
+    ArchiveAirdrop {
+        airdrop_index: u16,
+    },
+    // a shorter delay must not apply to actions proposed before it runs
+    SetTimelockDelay {
+        timelock_delay_ms: U64,
+    },
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum ActionKind {
+    ChangeSchedule,
+    DisableAirdrop,
+    ArchiveAirdrop,
+    SetTimelockDelay,
+}
+
+/// At most one action of each kind can be pending for an airdrop (None: the whole contract)
+pub type ActionSlot = (ActionKind, Option<u16>);
+
+impl AdminAction {
+    pub fn slot(&self) -> ActionSlot {
+        match self {
+            AdminAction::ChangeSchedule { airdrop_index, .. } => {
+                (ActionKind::ChangeSchedule, Some(*airdrop_index))
+            }
+            AdminAction::DisableAirdrop { airdrop_index, .. } => {
+                (ActionKind::DisableAirdrop, Some(*airdrop_index))
+            }
+            AdminAction::ArchiveAirdrop { airdrop_index } => {
+                (ActionKind::ArchiveAirdrop, Some(*airdrop_index))
+            }
+            AdminAction::SetTimelockDelay { .. } => (ActionKind::SetTimelockDelay, None),
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PendingAction {
+    pub action: AdminAction,
+    pub proposer_id: AccountId,
+    pub proposed_at_ms: u64,
+    pub executable_at_ms: u64,
+    // paid by the proposer, refunded when the action is executed or cancelled
+    pub storage_deposit: u128,
+}
+
+#[near_bindgen]
+impl GradualReleaseContract {
+    // anyone can execute a queued action once its delay has passed
+    pub fn execute_action(&mut self, action_id: u64) {
+        self.assert_not_paused();
+        let pending = self.internal_remove_action(action_id);
+        assert!(
+            get_current_epoch_millis() >= pending.executable_at_ms,
+            "Action {} can not be executed before {}",
+            action_id,
+            pending.executable_at_ms
+        );
+        log!("Executing action {}: {:?}", action_id, pending.action);
+        match pending.action {
+            AdminAction::ChangeSchedule {
+                airdrop_index,
+                start_timestamp_ms,
+                end_timestamp_ms,
+                force,
+            } => self.internal_change_schedule(
+                airdrop_index,
+                start_timestamp_ms,
+                end_timestamp_ms,
+                force,
+            ),
+            AdminAction::DisableAirdrop {
+                airdrop_index,
+                freeze_accrual,
+            } => {
+                let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+                airdrop.change_status(airdrop::AirdropStatus::Paused);
+                if freeze_accrual {
+                    airdrop.freeze_accrual();
+                }
+                events::Event::AirdropDisabled(vec![events::AirdropDisabledData {
+                    airdrop_index,
+                    freeze_accrual,
+                }])
+                .emit();
+            }
+            AdminAction::ArchiveAirdrop { airdrop_index } => {
+                self.airdrops[airdrop_index as u32]
+                    .live_mut()
+                    .change_status(airdrop::AirdropStatus::Ended);
+                events::Event::AirdropArchived(vec![events::AirdropData { airdrop_index }]).emit();
+            }
+            AdminAction::SetTimelockDelay { timelock_delay_ms } => {
+                log!(
+                    "Timelock delay changed from {} to {} ms",
+                    self.timelock_delay_ms,
+                    timelock_delay_ms.0
+                );
+                self.timelock_delay_ms = timelock_delay_ms.0;
+            }
+        }
+    }
+
+    // the proposer or the owner can drop a queued action
+    pub fn cancel_action(&mut self, action_id: u64) {
+        let pending = self
+            .pending_actions
+            .get(&action_id)
+            .expect("Action not found");
+        let account_id = env::predecessor_account_id();
+        require!(
+            account_id == pending.proposer_id || self.has_role(&account_id, Role::Owner),
+            "Only the proposer or the owner can cancel an action"
+        );
+        log!("{} cancelled action {}", account_id, action_id);
+        self.internal_remove_action(action_id);
+    }
+
+    // queued behind the current delay, returns the action id
+    // the attached deposit pays the storage of the action, see internal_propose_action
+    #[payable]
+    pub fn set_timelock_delay_ms(&mut self, timelock_delay_ms: U64) -> u64 {
+        self.assert_role(Role::Owner);
+        self.internal_propose_action(AdminAction::SetTimelockDelay { timelock_delay_ms })
+    }
+}
+
+impl GradualReleaseContract {
+    // queue an action, returns its id
+    // the attached deposit pays its storage, the excess is refunded
+    pub(crate) fn internal_propose_action(&mut self, action: AdminAction) -> u64 {
+        let slot = action.slot();
+        if let Some(action_id) = self.pending_action_slots.get(&slot) {
+            panic!(
+                "Action {} of the same kind is already pending, execute or cancel it first",
+                action_id
+            );
+        }
+        let storage_before = env::storage_usage();
+        let action_id = self.next_action_id;
+        self.next_action_id += 1;
+        let now = get_current_epoch_millis();
+        let executable_at_ms = now + self.timelock_delay_ms;
+        log!(
+            "Action {} queued, executable at {}: {:?}",
+            action_id,
+            executable_at_ms,
+            action
+        );
+        let mut pending = PendingAction {
+            action,
+            proposer_id: env::predecessor_account_id(),
+            proposed_at_ms: now,
+            executable_at_ms,
+            storage_deposit: 0,
+        };
+        self.pending_actions.insert(&action_id, &pending);
+        self.pending_action_slots.insert(&slot, &action_id);
+
+        let storage_cost =
+            env::storage_usage().saturating_sub(storage_before) as u128 * env::storage_byte_cost();
+        let attached_deposit = env::attached_deposit();
+        require!(
+            attached_deposit >= storage_cost,
+            format!(
+                "The attached deposit {} does not cover the storage cost {}",
+                attached_deposit, storage_cost
+            )
+        );
+        // same size, only the deposit changes
+        pending.storage_deposit = storage_cost;
+        self.pending_actions.insert(&action_id, &pending);
+        let refund = attached_deposit - storage_cost;
+        if refund > 1 {
+            Promise::new(pending.proposer_id).transfer(refund);
+        }
+        action_id
+    }
+
+    // drops a queued action and returns its storage deposit to the proposer
+    fn internal_remove_action(&mut self, action_id: u64) -> PendingAction {
+        let pending = self
+            .pending_actions
+            .remove(&action_id)
+            .expect("Action not found");
+        self.pending_action_slots.remove(&pending.action.slot());
+        if pending.storage_deposit > 0 {
+            Promise::new(pending.proposer_id.clone()).transfer(pending.storage_deposit);
+        }
+        pending
+    }
+
+    pub(crate) fn internal_change_schedule(
+        &mut self,
+        airdrop_index: u16,
+        start_timestamp_ms: U64,
+        end_timestamp_ms: U64,
+        force: bool,
+    ) {
+        assert!(
+            start_timestamp_ms.0 <= end_timestamp_ms.0,
+            "Start timestamp_ms must be before end timestamp_ms"
+        );
+        let new_schedule = airdrop::TimestampPeriod {
+            start_ms: start_timestamp_ms.0,
+            end_ms: end_timestamp_ms.0,
+        };
+        self.assert_schedule_not_behind(airdrop_index, &new_schedule, force);
+        let airdrop = self.airdrops[airdrop_index as u32].live_mut();
+        events::Event::ScheduleChanged(vec![events::ScheduleChangedData {
+            airdrop_index,
+            old_start_ms: airdrop.release_schedule.start_ms.into(),
+            old_end_ms: airdrop.release_schedule.end_ms.into(),
+            new_start_ms: start_timestamp_ms,
+            new_end_ms: end_timestamp_ms,
+            forced: force,
+        }])
+        .emit();
+        airdrop.release_schedule = new_schedule;
+    }
+
+    // users of a launched airdrop must never have less unlocked than with the current schedule
+    // the owner can force a change, the override is logged
+    pub(crate) fn assert_schedule_not_behind(
+        &self,
+        airdrop_index: u16,
+        new_schedule: &airdrop::TimestampPeriod,
+        force: bool,
+    ) {
+        let airdrop = self.airdrops[airdrop_index as u32].live();
+        // compare both schedules moved back by the frozen time
+        let frozen_ms = airdrop.frozen_ms(get_current_epoch_millis());
+        let new_effective_schedule = airdrop::TimestampPeriod {
+            start_ms: new_schedule.start_ms + frozen_ms,
+            end_ms: new_schedule.end_ms + frozen_ms,
+        };
+        if !airdrop.is_launched()
+            || airdrop.effective_schedule().is_never_ahead_of(
+                &new_effective_schedule,
+                airdrop.total_distributed,
+                get_current_epoch_millis(),
+            )
+        {
+            return;
+        }
+        assert!(
+            force,
+            "ERR: the new schedule of airdrop {} releases less than the current one",
+            airdrop_index
+        );
+        log!(
+            "WARN: schedule override for airdrop {}, from {}-{} to {}-{}",
+            airdrop_index,
+            airdrop.release_schedule.start_ms,
+            airdrop.release_schedule.end_ms,
+            new_schedule.start_ms,
+            new_schedule.end_ms
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use near_sdk::{bs58, Gas, GasWeight};
+
+use crate::*;
+
+// The owner (e.g. a DAO) upgrades the contract, so the contract account needs no full-access key.
+// The new code is deployed and migrate is called in the same batch: if migrate fails, the deploy is reverted.
+
+// staged wasm, removed by migrate after a successful upgrade
+pub(crate) const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
+// (account_id, deposit): who staged the code and the NEAR it paid for its storage
+pub(crate) const STAGED_CODE_DEPOSIT_KEY: &[u8] = b"STAGED_CODE_DEPOSIT";
+
+// the wasm is the raw input, too large to pass as a JSON argument
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn upgrade() {
+    env::setup_panic_hook();
+    let contract: GradualReleaseContract = env::state_read().expect("Not initialized");
+    assert_one_yocto();
+    contract.assert_role(Role::Owner);
+    let code = env::input().expect("No code");
+    internal_deploy_and_migrate(code);
+}
+
+// stores the wasm for deploy_staged_code, so the code can be checked by its hash before the deploy
+// staging deploys nothing. The attached NEAR pays the storage of the code, the excess is refunded.
+// Code staged before is replaced, its deposit goes back to the account that staged it
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn stage_code() {
+    env::setup_panic_hook();
+    let contract: GradualReleaseContract = env::state_read().expect("Not initialized");
+    require!(
+        env::attached_deposit() > 0,
+        "Requires attached deposit of at least 1 yoctoNEAR"
+    );
+    contract.assert_role(Role::Owner);
+    let account_id = env::predecessor_account_id();
+    let code = env::input().expect("No code");
+    internal_remove_staged_code();
+    let storage_before = env::storage_usage();
+    env::storage_write(STAGED_CODE_KEY, &code);
+    // the record has the same size whatever the deposit, it is written again below
+    write_staged_code_deposit(&account_id, 0);
+    let storage_cost =
+        env::storage_usage().saturating_sub(storage_before) as u128 * env::storage_byte_cost();
+    let attached_deposit = env::attached_deposit();
+    require!(
+        attached_deposit >= storage_cost,
+        format!(
+            "The attached deposit {} does not cover the storage cost {}",
+            attached_deposit, storage_cost
+        )
+    );
+    write_staged_code_deposit(&account_id, storage_cost);
+    if attached_deposit - storage_cost > 1 {
+        Promise::new(account_id.clone()).transfer(attached_deposit - storage_cost);
+    }
+    log!("{} staged code with hash {}", account_id, hash_code(&code));
+}
+
+#[near_bindgen]
+impl GradualReleaseContract {
+    // code_hash is the one from get_staged_code_hash, so the owner deploys the code it approved
+    #[payable]
+    pub fn deploy_staged_code(&mut self, code_hash: String) -> Promise {
+        assert_one_yocto();
+        self.assert_role(Role::Owner);
+        let code = env::storage_read(STAGED_CODE_KEY).expect("No staged code");
+        require!(
+            hash_code(&code) == code_hash,
+            "The staged code does not match code_hash"
+        );
+        internal_deploy_and_migrate(code)
+    }
+
+    // base58 sha256, as the code_hash of an account in near-cli and the explorers
+    pub fn get_staged_code_hash(&self) -> Option<String> {
+        env::storage_read(STAGED_CODE_KEY).map(|code| hash_code(&code))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_staged_code_deposit(account_id: &AccountId, deposit: u128) {
+    let record = (account_id.clone(), deposit)
+        .try_to_vec()
+        .expect("Can not serialize the staged code deposit");
+    env::storage_write(STAGED_CODE_DEPOSIT_KEY, &record);
+}
+
+// removes the staged code and returns its storage deposit to the account that staged it
+pub(crate) fn internal_remove_staged_code() {
+    env::storage_remove(STAGED_CODE_KEY);
+    if let Some(record) = env::storage_read(STAGED_CODE_DEPOSIT_KEY) {
+        env::storage_remove(STAGED_CODE_DEPOSIT_KEY);
+        let (account_id, deposit) = <(AccountId, u128)>::try_from_slice(&record)
+            .expect("Can not read the staged code deposit");
+        if deposit > 0 {
+            Promise::new(account_id).transfer(deposit);
+        }
+    }
+}
+
+pub(crate) fn hash_code(code: &[u8]) -> String {
+    bs58::encode(env::sha256(code)).into_string()
+}
+
+// migrate gets all the gas left by this call
+pub(crate) fn internal_deploy_and_migrate(code: Vec<u8>) -> Promise {
+    log!(
+        "Upgrading {} to code with hash {}",
+        env::current_account_id(),
+        hash_code(&code)
+    );
+    Promise::new(env::current_account_id())
+        .deploy_contract(code)
+        .function_call_weight("migrate".into(), vec![], 0, Gas(0), GasWeight(1))
+}
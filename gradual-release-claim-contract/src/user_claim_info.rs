@@ -8,6 +8,10 @@ pub struct UserClaimInfo {
     pub airdrop_index: u16,
     pub assigned_tokens: u128,
     pub claimed_tokens: u128,
+    // compliance hold, set by freeze_user_claim. The user can not claim while frozen
+    pub frozen_reason: Option<String>,
+    // paid the storage of this row, gets it back when the row is removed. None if the contract paid it (v2.0.0 claims)
+    pub storage_payer_id: Option<AccountId>,
 }
 
 impl UserClaimInfo {
@@ -27,20 +27,57 @@ pub fn proportional(amount: u128, numerator: u128, denominator: u128) -> u128 {
     (U256::from(amount) * U256::from(numerator) / U256::from(denominator)).as_u128()
 }
 
+pub fn add_to_token_total(map: &mut UnorderedMap<Token, u128>, token: &Token, amount: u128) {
+    let current_amount = map.get(token).unwrap_or(0);
+    map.insert(token, &(current_amount + amount));
+}
+
+pub fn sub_from_token_total(map: &mut UnorderedMap<Token, u128>, token: &Token, amount: u128) {
+    let current_amount = map.get(token).unwrap_or(0);
+    map.insert(token, &(current_amount - amount));
+}
+
+// for payable methods that only need the deposit when they queue an action
+pub fn refund_attached_deposit() {
+    if env::attached_deposit() > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+    }
+}
+
 pub fn parse_token_amount(amount_string: &str, token_decimals: u8) -> u128 {
+    try_parse_token_amount(amount_string, token_decimals).unwrap_or_else(|err| panic!("{}", err))
+}
+
+pub fn try_parse_token_amount(amount_string: &str, token_decimals: u8) -> Result<u128, String> {
     let dec_point_position = amount_string.find('.').unwrap_or(amount_string.len());
     let (amount_no_dec_point, current_decimals) = if dec_point_position == amount_string.len() {
         (amount_string.to_string(), 0 as u32)
     } else {
         let current_decimals = amount_string.len() - dec_point_position - 1;
-        assert!(
-            current_decimals <= token_decimals as usize,
-            "Too many decimals in the string amount"
-        );
+        if current_decimals > token_decimals as usize {
+            return Err("Too many decimals in the string amount".into());
+        }
         let mut amount_no_dec_point = amount_string.to_string();
         amount_no_dec_point.remove(dec_point_position);
         (amount_no_dec_point, current_decimals as u32)
     };
-    let amount_u128 = amount_no_dec_point.parse::<u128>().unwrap();
-    amount_u128 * 10u128.pow(token_decimals as u32 - current_decimals)
+    let amount_u128 = amount_no_dec_point
+        .parse::<u128>()
+        .map_err(|_| format!("invalid amount {:?}", amount_string))?;
+    amount_u128
+        .checked_mul(10u128.pow(token_decimals as u32 - current_decimals))
+        .ok_or_else(|| format!("amount {:?} is too large", amount_string))
+}
+
+// one row of add_claims data: a valid NEAR account id and a decimal amount
+pub fn parse_claim_row(
+    row: &(String, String),
+    token_decimals: u8,
+) -> Result<(AccountId, u128), String> {
+    let account_id = row
+        .0
+        .parse::<AccountId>()
+        .map_err(|_| format!("invalid account id {:?}", row.0))?;
+    let amount = try_parse_token_amount(&row.1, token_decimals)?;
+    Ok((account_id, amount))
 }
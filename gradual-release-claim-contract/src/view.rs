@@ -17,22 +17,138 @@ pub struct ContractInfoJSON {
     pub operator_id: String,
     pub airdrop_count: u16,
     pub user_count: u64,
+    pub paused: bool,
+    pub platform_fee_bp: u16,
+    pub state_version: u16,
+    pub storage_treasury_id: Option<AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PurgeProgressJSON {
+    pub next_index: u32,
+    pub user_count: u64,
+    // claims of the airdrop not removed yet
+    pub claims_left: u32,
+    pub purged: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PurgedAirdropJSON {
+    pub airdrop_index: u16,
+    pub token_contract: AccountId,
+    pub total_distributed: U128,
+    pub total_claimed: U128,
+    pub purged_at_ms: U64,
+}
+
+// claims of an airdrop read so far by audit, pass them back to read the next page
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditAirdropSumJSON {
+    pub airdrop_index: u16,
+    pub assigned_tokens: U128,
+    pub claimed_tokens: U128,
+    // claims in the per-airdrop layout, legacy claims are not counted in claim_count
+    pub claim_count: u32,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditTokenJSON {
+    pub token_contract: AccountId,
+    pub total_in_claims: U128,
+    // assigned_tokens - claimed_tokens of the claims, cancelled airdrops excluded
+    pub recomputed: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditMismatchJSON {
+    // the token or the airdrop that does not match
+    pub token_contract: Option<AccountId>,
+    pub airdrop_index: Option<u16>,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuditPageJSON {
+    pub next_index: u32,
+    pub user_count: u64,
+    // true on the final page, tokens and mismatches are only filled there
+    pub done: bool,
+    pub sums: Vec<AuditAirdropSumJSON>,
+    pub tokens: Vec<AuditTokenJSON>,
+    pub mismatches: Vec<AuditMismatchJSON>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CleanupProgressJSON {
+    pub next_index: u32,
+    pub user_count: u64,
+    pub removed_claims: u32,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingNominationsJSON {
+    pub owner_id: Option<AccountId>,
+    pub operator_id: Option<AccountId>,
+    pub operator_accept_after_ms: Option<U64>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingActionJSON {
+    pub action_id: U64,
+    pub action: timelock::AdminAction,
+    pub proposer_id: AccountId,
+    pub proposed_at_ms: U64,
+    pub executable_at_ms: U64,
+    pub storage_deposit: U128,
 }
 
 #[derive(Serialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct AirdropJSON {
     pub airdrop_index: u16,
-    pub status_code: u8,
+    pub status: airdrop::AirdropStatus,
     pub enabled: bool,
     pub title: String,
     pub token_contract: AccountId,
+    pub manager_id: AccountId,
+    pub sponsor_id: Option<AccountId>,
+    pub deposited_tokens: U128,
     pub token_symbol: String,
     pub token_decimals: u8,
     pub release_schedule_start_ms: U64,
     pub release_schedule_end_ms: U64,
+    // release end moved back by the accrual freezes, grows while frozen_since_ms is set
+    pub effective_end_ms: U64,
+    pub frozen_since_ms: Option<U64>,
+    pub total_frozen_ms: U64,
+    pub allowlist_required: bool,
+    // closed with cancel_airdrop before launch
+    pub cancelled: bool,
+    // NEAR held for the storage of this airdrop, its claims record their own payer
+    pub storage_payer_id: AccountId,
+    pub storage_deposit: U128,
     pub total_distributed: U128,
     pub total_claimed: U128,
+    // unclaimed tokens of the claims removed by purge_airdrop
+    pub total_reclaimed: U128,
+    // the refund of cancel_airdrop failed, see retry_cancel_refund
+    pub failed_cancel_refund: Option<U128>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatusChangeJSON {
+    pub status: airdrop::AirdropStatus,
+    pub timestamp_ms: U64,
 }
 
 // ---- as JSON to return from view calls ---
@@ -65,10 +181,25 @@ pub struct ClaimInfoJSON {
     /// to zero after a claim, and will increase on each minute boundary until release_end_ms
     pub available_tokens_now: U128,
 
-    /// start of the gradual release period
+    /// start of the gradual release period, moved back by accrual freezes
     pub release_start_ms: U64,
-    /// end of the gradual release period
+    /// end of the gradual release period, moved back by accrual freezes
     pub release_end_ms: U64,
+
+    /// compliance hold, the user can not claim while frozen
+    pub frozen: bool,
+    /// why the claim is frozen, to show to the user
+    pub frozen_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvalidClaimRowJSON {
+    // index in data
+    pub row: u32,
+    pub account_id: String,
+    pub amount: String,
+    pub error: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -87,12 +218,76 @@ impl GradualReleaseContract {
         self.operator_id.to_string()
     }
 
+    // proposed owner and operator, waiting to accept
+    pub fn get_pending_nominations(&self) -> PendingNominationsJSON {
+        PendingNominationsJSON {
+            owner_id: self.pending_owner_id.clone(),
+            operator_id: self.pending_operator_id.clone(),
+            operator_accept_after_ms: self
+                .pending_operator_id
+                .as_ref()
+                .map(|_| self.operator_accept_after_ms.into()),
+        }
+    }
+
+    // queued admin actions, see timelock.rs
+    pub fn get_pending_actions(&self, from_index: u32, limit: u32) -> Vec<PendingActionJSON> {
+        let keys = self.pending_actions.keys_as_vector();
+        let values = self.pending_actions.values_as_vector();
+        let start = from_index as u64;
+        (start..std::cmp::min(start + limit as u64, keys.len()))
+            .map(|index| {
+                let pending = values.get(index).unwrap();
+                PendingActionJSON {
+                    action_id: keys.get(index).unwrap().into(),
+                    action: pending.action,
+                    proposer_id: pending.proposer_id,
+                    proposed_at_ms: pending.proposed_at_ms.into(),
+                    executable_at_ms: pending.executable_at_ms.into(),
+                    storage_deposit: pending.storage_deposit.into(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_timelock_delay_ms(&self) -> U64 {
+        self.timelock_delay_ms.into()
+    }
+
+    // all accounts holding a role, including owner_id and operator_id
+    pub fn get_role_holders(&self, role: Role) -> Vec<AccountId> {
+        let mut result = Vec::new();
+        if roles::OWNER_IMPLICIT_ROLES.contains(&role) {
+            result.push(self.owner_id.clone());
+        }
+        if roles::OPERATOR_IMPLICIT_ROLES.contains(&role) {
+            result.push(self.operator_id.clone());
+        }
+        for (r, account_id) in self.roles.iter() {
+            if r == role && !result.contains(&account_id) {
+                result.push(account_id);
+            }
+        }
+        result
+    }
+
+    pub fn get_account_roles(&self, account_id: AccountId) -> Vec<Role> {
+        roles::ALL_ROLES
+            .into_iter()
+            .filter(|role| self.has_role(&account_id, *role))
+            .collect()
+    }
+
     pub fn get_contract_info(&self) -> ContractInfoJSON {
         ContractInfoJSON {
             owner_id: self.owner_id.as_str().into(),
             operator_id: self.operator_id.as_str().into(),
             airdrop_count: self.airdrops.len() as u16,
-            user_count: self.available_claims.len(),
+            user_count: self.user_airdrops.len() + self.legacy_claims.len(),
+            paused: self.paused,
+            platform_fee_bp: self.platform_fee_bp,
+            state_version: migrate::read_state_version(),
+            storage_treasury_id: self.storage_treasury_id.clone(),
         }
     }
 
@@ -103,6 +298,19 @@ impl GradualReleaseContract {
             .into()
     }
 
+    // platform fees collected from sponsored airdrops, not yet withdrawn
+    pub fn get_platform_fees(&self, token_contract: AccountId) -> U128 {
+        self.platform_fees_per_token
+            .get(&token_contract)
+            .unwrap_or(0)
+            .into()
+    }
+
+    // NEAR the account paid for the storage of claims and list entries, not yet refunded
+    pub fn get_storage_deposit(&self, account_id: AccountId) -> U128 {
+        self.storage_deposits.get(&account_id).unwrap_or(0).into()
+    }
+
     pub fn get_airdrops(&self) -> Vec<AirdropJSON> {
         self.internal_get_airdrops(false)
     }
@@ -110,48 +318,134 @@ impl GradualReleaseContract {
         self.internal_get_airdrops(true)
     }
 
+    // every status change of an airdrop, for auditors
+    pub fn get_airdrop_status_history(&self, airdrop_index: u16) -> Vec<StatusChangeJSON> {
+        self.airdrops[airdrop_index as u32]
+            .live()
+            .status_history
+            .iter()
+            .map(|change| StatusChangeJSON {
+                status: change.status,
+                timestamp_ms: change.timestamp_ms.into(),
+            })
+            .collect()
+    }
+
+    // the final totals of the purged airdrops
+    pub fn get_purged_airdrops(&self) -> Vec<PurgedAirdropJSON> {
+        self.airdrops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                airdrop::AirdropEntry::Purged(p) => Some(PurgedAirdropJSON {
+                    airdrop_index: index as u16,
+                    token_contract: p.token_contract.clone(),
+                    total_distributed: U128(p.total_distributed),
+                    total_claimed: U128(p.total_claimed),
+                    purged_at_ms: p.purged_at_ms.into(),
+                }),
+                airdrop::AirdropEntry::Live(_) => None,
+            })
+            .collect()
+    }
+
+    // purged airdrops are not listed, use airdrop_index rather than the position in the list
     pub(crate) fn internal_get_airdrops(&self, include_disabled: bool) -> Vec<AirdropJSON> {
         self.airdrops
             .iter()
             .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                airdrop::AirdropEntry::Live(a) => Some((index, a)),
+                airdrop::AirdropEntry::Purged(_) => None,
+            })
             .filter(|(_, a)| include_disabled || a.is_enabled())
             .map(|(index, a)| AirdropJSON {
                 airdrop_index: index as u16,
                 enabled: a.is_enabled(),
-                status_code: a.status_code,
+                status: a.status,
                 title: a.title.clone(),
                 token_contract: a.token_contract.clone(),
+                manager_id: a.manager_id.clone(),
+                sponsor_id: a.sponsor_id.clone(),
+                deposited_tokens: U128(a.deposited_tokens),
                 token_symbol: a.token_symbol.clone(),
                 token_decimals: a.token_decimals,
                 release_schedule_start_ms: a.release_schedule.start_ms.into(),
                 release_schedule_end_ms: a.release_schedule.end_ms.into(),
+                effective_end_ms: a.effective_schedule().end_ms.into(),
+                frozen_since_ms: a.frozen_since_ms.map(U64),
+                total_frozen_ms: a.total_frozen_ms.into(),
+                allowlist_required: a.allowlist_required,
+                cancelled: a.cancelled,
+                storage_payer_id: a.storage_payer_id.clone(),
+                storage_deposit: U128(a.storage_deposit),
                 total_distributed: U128(a.total_distributed),
                 total_claimed: U128(a.total_claimed),
+                total_reclaimed: U128(a.total_reclaimed),
+                failed_cancel_refund: a
+                    .cancel_refund
+                    .as_ref()
+                    .filter(|refund| refund.failed)
+                    .map(|refund| U128(refund.amount)),
             })
             .collect()
     }
 
     // get all information for a single voter: voter + locking-positions + voting-positions
     pub fn get_user_claims(&self, account_id: &AccountId) -> Vec<ClaimInfoJSON> {
-        self.claims_to_json(self.internal_get_claims(&account_id).into_iter(), false)
+        self.claims_to_json(self.internal_get_user_claims(account_id).into_iter(), false)
     }
 
     // get all information for a single voter: voter + locking-positions + voting-positions
     pub fn get_user_claims_including_inactive(&self, account_id: &AccountId) -> Vec<ClaimInfoJSON> {
-        self.claims_to_json(self.internal_get_claims(&account_id).into_iter(), true)
+        self.claims_to_json(self.internal_get_user_claims(account_id).into_iter(), true)
+    }
+
+    // check an add_claims data list without writing state, returns the rows add_claims would reject
+    pub fn validate_claims(
+        &self,
+        airdrop_index: u16,
+        data: Vec<(String, String)>,
+    ) -> Vec<InvalidClaimRowJSON> {
+        let token_decimals = self.airdrops[airdrop_index as u32].live().token_decimals;
+        data.into_iter()
+            .enumerate()
+            .filter_map(|(row, item)| {
+                let error = match parse_claim_row(&item, token_decimals) {
+                    Err(err) => err,
+                    Ok((account_id, _)) if self.denylist.get(&account_id).is_some() => {
+                        format!("{} is denylisted", account_id)
+                    }
+                    Ok(_) => return None,
+                };
+                Some(InvalidClaimRowJSON {
+                    row: row as u32,
+                    account_id: item.0,
+                    amount: item.1,
+                    error,
+                })
+            })
+            .collect()
     }
 
     // get all information for multiple voters, by index: Vec<voter + locking-positions + voting-positions>
+    // migrated users first, then the ones still in the legacy layout
+    // while migrate_claims runs the order changes between calls
     pub fn get_users(&self, from_index: u32, limit: u32) -> Vec<UserClaimsJSON> {
-        let keys = self.available_claims.keys_as_vector();
-        let voters_len = keys.len() as u64;
+        let keys = self.user_airdrops.keys_as_vector();
+        let legacy_keys = self.legacy_claims.keys_as_vector();
+        let voters_len = keys.len() + legacy_keys.len();
         let start = from_index as u64;
         let limit = limit as u64;
 
         let mut results = Vec::<UserClaimsJSON>::new();
         for index in start..std::cmp::min(start + limit, voters_len) {
-            let account_id = keys.get(index).unwrap();
-            let claims = self.available_claims.get(&account_id).unwrap();
+            let account_id = if index < keys.len() {
+                keys.get(index).unwrap()
+            } else {
+                legacy_keys.get(index - keys.len()).unwrap()
+            };
+            let claims = self.internal_get_user_claims(&account_id);
             results.push(UserClaimsJSON {
                 account_id: account_id.clone(),
                 claims: self.claims_to_json(claims.into_iter(), true),
@@ -166,8 +460,9 @@ impl GradualReleaseContract {
     {
         let mut result = Vec::new();
         for claim in iter {
-            let airdrop = &self.airdrops[claim.airdrop_index as usize];
+            let airdrop = self.airdrops[claim.airdrop_index as u32].live();
             if airdrop.is_enabled() && (include_inactive || claim.is_active()) {
+                let effective_schedule = airdrop.effective_schedule();
                 result.push(ClaimInfoJSON {
                     is_active: claim.is_active(),
                     airdrop_index: claim.airdrop_index,
@@ -177,9 +472,11 @@ impl GradualReleaseContract {
                     token_contract: airdrop.token_contract.clone(),
                     assigned_tokens: U128(claim.assigned_tokens),
                     claimed_tokens: U128(claim.claimed_tokens),
-                    available_tokens_now: claim.available_now(&airdrop.release_schedule).into(),
-                    release_start_ms: airdrop.release_schedule.start_ms.into(),
-                    release_end_ms: airdrop.release_schedule.end_ms.into(),
+                    available_tokens_now: claim.available_now(&effective_schedule).into(),
+                    release_start_ms: effective_schedule.start_ms.into(),
+                    release_end_ms: effective_schedule.end_ms.into(),
+                    frozen: claim.frozen_reason.is_some(),
+                    frozen_reason: claim.frozen_reason.clone(),
                 })
             };
         }
@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::ledger::{Ledger, PlatformFees, Totals};
+
+// amounts in token units, as strings: spreadsheets round integers above 2^53
+#[derive(Serialize)]
+struct AirdropRow<'a> {
+    airdrop_index: u16,
+    title: &'a str,
+    token_contract: &'a str,
+    status: &'a str,
+    assigned: String,
+    claimed: String,
+    rolled_back: String,
+    revoked: String,
+    outstanding: String,
+    deposited: String,
+    platform_fee: String,
+    refunded: String,
+    // empty if the airdrop is not sponsored
+    unallocated: String,
+}
+
+#[derive(Serialize)]
+struct UserRow<'a> {
+    account_id: &'a str,
+    airdrop_index: u16,
+    token_contract: &'a str,
+    assigned: String,
+    claimed: String,
+    rolled_back: String,
+    revoked: String,
+    outstanding: String,
+}
+
+#[derive(Serialize)]
+struct PlatformFeesRow<'a> {
+    token_contract: &'a str,
+    collected: String,
+    withdrawn: String,
+    balance: String,
+}
+
+/// one row per airdrop
+pub fn write_airdrops_csv<W: Write>(ledger: &Ledger, writer: W) -> anyhow::Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for (airdrop_index, airdrop) in &ledger.airdrops {
+        let totals = &airdrop.totals;
+        csv_writer.serialize(AirdropRow {
+            airdrop_index: *airdrop_index,
+            title: &airdrop.title,
+            token_contract: &airdrop.token_contract,
+            status: &airdrop.status,
+            assigned: totals.assigned.to_string(),
+            claimed: totals.claimed.to_string(),
+            rolled_back: totals.rolled_back.to_string(),
+            revoked: totals.revoked.to_string(),
+            outstanding: totals.outstanding().to_string(),
+            deposited: airdrop.funds.deposited.to_string(),
+            platform_fee: airdrop.funds.platform_fee.to_string(),
+            refunded: airdrop.funds.refunded.to_string(),
+            unallocated: airdrop
+                .unallocated()
+                .map_or(String::new(), |unallocated| unallocated.to_string()),
+        })?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// one row per token with platform fees
+pub fn write_platform_fees_csv<W: Write>(ledger: &Ledger, writer: W) -> anyhow::Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for (token_contract, fees) in &ledger.platform_fees {
+        let PlatformFees {
+            collected,
+            withdrawn,
+        } = fees;
+        csv_writer.serialize(PlatformFeesRow {
+            token_contract,
+            collected: collected.to_string(),
+            withdrawn: withdrawn.to_string(),
+            balance: fees.balance().to_string(),
+        })?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// one row per user and airdrop, sorted by account
+pub fn write_users_csv<W: Write>(ledger: &Ledger, writer: W) -> anyhow::Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for ((account_id, airdrop_index), totals) in &ledger.users {
+        let Totals {
+            assigned,
+            claimed,
+            rolled_back,
+            revoked,
+        } = totals;
+        csv_writer.serialize(UserRow {
+            account_id,
+            airdrop_index: *airdrop_index,
+            token_contract: ledger
+                .airdrops
+                .get(airdrop_index)
+                .map_or("", |airdrop| &airdrop.token_contract),
+            assigned: assigned.to_string(),
+            claimed: claimed.to_string(),
+            rolled_back: rolled_back.to_string(),
+            revoked: revoked.to_string(),
+            outstanding: totals.outstanding().to_string(),
+        })?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Deserializer};
+
+/// `standard` of the contract events, see events.rs in the contract
+pub const EVENT_STANDARD: &str = "gradual_release_claim";
+/// events of another major version are rejected, a new minor version only adds events or fields
+pub const EVENT_MAJOR_VERSION: &str = "1";
+
+const EVENT_PREFIX: &str = "EVENT_JSON:";
+
+/// NEP-297 events logged by the contract, version 1
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    AirdropRegistered(Vec<AirdropRegistered>),
+    ClaimsAdded(Vec<ClaimsAdded>),
+    ClaimUpdated(Vec<ClaimUpdated>),
+    AirdropFunded(Vec<AirdropData>),
+    AirdropEnabled(Vec<AirdropData>),
+    AirdropDisabled(Vec<AirdropDisabled>),
+    AirdropArchived(Vec<AirdropData>),
+    AirdropClosed(Vec<AirdropData>),
+    AirdropPurged(Vec<AirdropData>),
+    AirdropCancelled(Vec<AirdropData>),
+    ScheduleChanged(Vec<ScheduleChanged>),
+    TokensClaimed(Vec<ClaimData>),
+    ClaimRolledBack(Vec<ClaimData>),
+    ClaimsRemoved(Vec<ClaimRemoved>),
+    ClaimFrozen(Vec<ClaimFrozen>),
+    ClaimUnfrozen(Vec<ClaimUnfrozen>),
+    ContractPaused(Vec<AccountData>),
+    ContractUnpaused(Vec<AccountData>),
+    RoleGranted(Vec<RoleData>),
+    RoleRevoked(Vec<RoleData>),
+    OwnerChanged(Vec<AccountChange>),
+    OperatorChanged(Vec<AccountChange>),
+    AirdropManagerChanged(Vec<AirdropManagerChanged>),
+    SponsorDeposited(Vec<SponsorDeposited>),
+    SponsorRefunded(Vec<RefundData>),
+    CancelRefunded(Vec<RefundData>),
+    PlatformFeeChanged(Vec<PlatformFeeChanged>),
+    PlatformFeesWithdrawn(Vec<PlatformFeesWithdrawn>),
+    DenylistAdded(Vec<AccountData>),
+    DenylistRemoved(Vec<AccountData>),
+    AllowlistAdded(Vec<AllowlistData>),
+    AllowlistRemoved(Vec<AllowlistData>),
+}
+
+impl Event {
+    /// every event name of `Event`, other names are from a later minor version
+    pub const NAMES: [&'static str; 32] = [
+        "airdrop_registered",
+        "claims_added",
+        "claim_updated",
+        "airdrop_funded",
+        "airdrop_enabled",
+        "airdrop_disabled",
+        "airdrop_archived",
+        "airdrop_closed",
+        "airdrop_purged",
+        "airdrop_cancelled",
+        "schedule_changed",
+        "tokens_claimed",
+        "claim_rolled_back",
+        "claims_removed",
+        "claim_frozen",
+        "claim_unfrozen",
+        "contract_paused",
+        "contract_unpaused",
+        "role_granted",
+        "role_revoked",
+        "owner_changed",
+        "operator_changed",
+        "airdrop_manager_changed",
+        "sponsor_deposited",
+        "sponsor_refunded",
+        "cancel_refunded",
+        "platform_fee_changed",
+        "platform_fees_withdrawn",
+        "denylist_added",
+        "denylist_removed",
+        "allowlist_added",
+        "allowlist_removed",
+    ];
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AirdropRegistered {
+    pub airdrop_index: u16,
+    pub title: String,
+    pub token_contract: String,
+    pub manager_id: String,
+    pub sponsor_id: Option<String>,
+    #[serde(deserialize_with = "from_string")]
+    pub start_ms: u64,
+    #[serde(deserialize_with = "from_string")]
+    pub end_ms: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimsAdded {
+    pub airdrop_index: u16,
+    pub total_amount: Amount,
+    /// (account_id, amount) rows
+    pub claims: Vec<(String, Amount)>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimUpdated {
+    pub airdrop_index: u16,
+    pub account_id: String,
+    pub old_amount: Amount,
+    pub new_amount: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AirdropData {
+    pub airdrop_index: u16,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AirdropDisabled {
+    pub airdrop_index: u16,
+    pub freeze_accrual: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduleChanged {
+    pub airdrop_index: u16,
+    #[serde(deserialize_with = "from_string")]
+    pub old_start_ms: u64,
+    #[serde(deserialize_with = "from_string")]
+    pub old_end_ms: u64,
+    #[serde(deserialize_with = "from_string")]
+    pub new_start_ms: u64,
+    #[serde(deserialize_with = "from_string")]
+    pub new_end_ms: u64,
+    pub forced: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimData {
+    pub airdrop_index: u16,
+    pub account_id: String,
+    pub amount: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimRemoved {
+    pub airdrop_index: u16,
+    pub account_id: String,
+    /// no longer owed, 0 for used claims and claims of a cancelled airdrop
+    pub unclaimed_amount: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimFrozen {
+    pub airdrop_index: u16,
+    pub account_id: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaimUnfrozen {
+    pub airdrop_index: u16,
+    pub account_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountData {
+    pub account_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RoleData {
+    pub account_id: String,
+    pub role: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountChange {
+    pub old_account_id: String,
+    pub new_account_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AirdropManagerChanged {
+    pub airdrop_index: u16,
+    pub old_manager_id: String,
+    pub new_manager_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SponsorDeposited {
+    pub airdrop_index: u16,
+    pub sponsor_id: String,
+    pub token_contract: String,
+    /// added to the airdrop, after the platform fee
+    pub amount: Amount,
+    pub platform_fee: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RefundData {
+    pub airdrop_index: u16,
+    pub account_id: String,
+    pub amount: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PlatformFeeChanged {
+    pub old_fee_bp: u16,
+    pub new_fee_bp: u16,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct PlatformFeesWithdrawn {
+    pub token_contract: String,
+    pub receiver_id: String,
+    pub amount: Amount,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AllowlistData {
+    pub airdrop_index: u16,
+    pub account_id: String,
+}
+
+/// token amount, a decimal string in the events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount(pub u128);
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        from_string(deserializer).map(Amount)
+    }
+}
+
+fn from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize)]
+struct EventLog {
+    standard: String,
+    version: String,
+    event: String,
+    data: serde_json::Value,
+}
+
+/// parses a log line. Returns None for plain logs, other standards and events of a later minor version
+pub fn parse_event_log(log: &str) -> anyhow::Result<Option<Event>> {
+    let json = match log.strip_prefix(EVENT_PREFIX) {
+        Some(json) => json,
+        None => return Ok(None),
+    };
+    let event_log: EventLog =
+        serde_json::from_str(json).with_context(|| format!("invalid event log {}", log))?;
+    if event_log.standard != EVENT_STANDARD {
+        return Ok(None);
+    }
+    if event_log.version.split('.').next() != Some(EVENT_MAJOR_VERSION) {
+        bail!(
+            "unsupported {} version {}, this parser reads {}.x",
+            EVENT_STANDARD,
+            event_log.version,
+            EVENT_MAJOR_VERSION
+        );
+    }
+    if !Event::NAMES.contains(&event_log.event.as_str()) {
+        return Ok(None);
+    }
+    let event = serde_json::from_value(serde_json::json!({
+        "event": event_log.event,
+        "data": event_log.data,
+    }))
+    .with_context(|| format!("invalid {} event {}", event_log.event, log))?;
+    Ok(Some(event))
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use crate::event::Event;
+use crate::outcome::EventRecord;
+
+/// token amounts of a user claim, or of a whole airdrop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    /// added with add_claims, and raised with update_claim
+    pub assigned: u128,
+    /// transferred to the user
+    pub claimed: u128,
+    /// claims whose token transfer failed, claimable again. Not included in `claimed`
+    pub rolled_back: u128,
+    /// lowered or removed with update_claim / remove_claim, cancelled with the airdrop,
+    /// or left unclaimed in a claim removed by purge_airdrop
+    pub revoked: u128,
+}
+
+impl Totals {
+    /// still owed to the users
+    pub fn outstanding(&self) -> u128 {
+        self.assigned.saturating_sub(self.revoked + self.claimed)
+    }
+
+    fn add(&mut self, other: &Totals) {
+        self.assigned += other.assigned;
+        self.claimed += other.claimed;
+        self.rolled_back += other.rolled_back;
+        self.revoked += other.revoked;
+    }
+}
+
+/// token amounts deposited to an airdrop and refunded from it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Funds {
+    /// deposited by the sponsor, after the platform fee
+    pub deposited: u128,
+    /// kept by the platform from the sponsor deposits
+    pub platform_fee: u128,
+    /// returned to the sponsor, or to refund_to when a cancelled airdrop was not sponsored
+    pub refunded: u128,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AirdropLedger {
+    /// empty if the airdrop was registered before the first transaction read
+    pub title: String,
+    pub token_contract: String,
+    pub sponsored: bool,
+    /// registered, funded, enabled, disabled, archived, closed, cancelled or purged
+    pub status: String,
+    pub totals: Totals,
+    pub funds: Funds,
+}
+
+impl AirdropLedger {
+    /// sponsor deposits not in claims nor refunded, revoked claims go back to the deposit.
+    /// None if the airdrop is not sponsored
+    pub fn unallocated(&self) -> Option<u128> {
+        self.sponsored.then(|| {
+            (self.funds.deposited + self.totals.revoked)
+                .saturating_sub(self.totals.assigned + self.funds.refunded)
+        })
+    }
+}
+
+/// platform fees of a token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlatformFees {
+    pub collected: u128,
+    pub withdrawn: u128,
+}
+
+impl PlatformFees {
+    /// still in the contract
+    pub fn balance(&self) -> u128 {
+        self.collected.saturating_sub(self.withdrawn)
+    }
+}
+
+/// per-airdrop and per-user totals, rebuilt from the contract events
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub airdrops: BTreeMap<u16, AirdropLedger>,
+    /// by (account_id, airdrop_index)
+    pub users: BTreeMap<(String, u16), Totals>,
+    /// by token_contract
+    pub platform_fees: BTreeMap<String, PlatformFees>,
+}
+
+impl Ledger {
+    pub fn from_events(records: &[EventRecord]) -> Self {
+        let mut ledger = Ledger::default();
+        for record in records {
+            ledger.apply(&record.event);
+        }
+        ledger
+    }
+
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::AirdropRegistered(data) => {
+                for registered in data {
+                    let airdrop = self.airdrop(registered.airdrop_index);
+                    airdrop.title = registered.title.clone();
+                    airdrop.token_contract = registered.token_contract.clone();
+                    airdrop.sponsored = registered.sponsor_id.is_some();
+                    airdrop.status = "registered".into();
+                }
+            }
+            Event::ClaimsAdded(data) => {
+                for added in data {
+                    for (account_id, amount) in &added.claims {
+                        self.change(account_id, added.airdrop_index, |totals| {
+                            totals.assigned += amount.0
+                        });
+                    }
+                }
+            }
+            Event::ClaimUpdated(data) => {
+                for updated in data {
+                    let (old_amount, new_amount) = (updated.old_amount.0, updated.new_amount.0);
+                    self.change(&updated.account_id, updated.airdrop_index, |totals| {
+                        if new_amount >= old_amount {
+                            totals.assigned += new_amount - old_amount;
+                        } else {
+                            totals.revoked += old_amount - new_amount;
+                        }
+                    });
+                }
+            }
+            Event::TokensClaimed(data) => {
+                for claim in data {
+                    self.change(&claim.account_id, claim.airdrop_index, |totals| {
+                        totals.claimed += claim.amount.0
+                    });
+                }
+            }
+            Event::ClaimRolledBack(data) => {
+                for claim in data {
+                    self.change(&claim.account_id, claim.airdrop_index, |totals| {
+                        totals.rolled_back += claim.amount.0
+                    });
+                }
+            }
+            Event::ClaimsRemoved(data) => {
+                // the user totals are kept, only the unclaimed tokens are revoked
+                for removed in data {
+                    self.change(&removed.account_id, removed.airdrop_index, |totals| {
+                        totals.revoked += removed.unclaimed_amount.0
+                    });
+                }
+            }
+            Event::AirdropFunded(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "funded")
+            }
+            Event::AirdropEnabled(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "enabled")
+            }
+            Event::AirdropDisabled(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "disabled")
+            }
+            Event::AirdropArchived(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "archived")
+            }
+            Event::AirdropClosed(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "closed")
+            }
+            Event::AirdropPurged(data) => {
+                self.set_status(data.iter().map(|d| d.airdrop_index), "purged")
+            }
+            Event::AirdropCancelled(data) => {
+                for cancelled in data {
+                    // nothing was claimed from a cancelled airdrop, every claim is revoked
+                    let users: Vec<String> = self
+                        .users
+                        .keys()
+                        .filter(|(_, airdrop_index)| *airdrop_index == cancelled.airdrop_index)
+                        .map(|(account_id, _)| account_id.clone())
+                        .collect();
+                    for account_id in users {
+                        self.change(&account_id, cancelled.airdrop_index, |totals| {
+                            totals.revoked += totals.outstanding()
+                        });
+                    }
+                }
+                self.set_status(data.iter().map(|d| d.airdrop_index), "cancelled");
+            }
+            Event::SponsorDeposited(data) => {
+                for deposit in data {
+                    let funds = &mut self.airdrop(deposit.airdrop_index).funds;
+                    funds.deposited += deposit.amount.0;
+                    funds.platform_fee += deposit.platform_fee.0;
+                    self.platform_fees
+                        .entry(deposit.token_contract.clone())
+                        .or_default()
+                        .collected += deposit.platform_fee.0;
+                }
+            }
+            Event::SponsorRefunded(data) | Event::CancelRefunded(data) => {
+                for refund in data {
+                    self.airdrop(refund.airdrop_index).funds.refunded += refund.amount.0;
+                }
+            }
+            Event::PlatformFeesWithdrawn(data) => {
+                for withdrawn in data {
+                    self.platform_fees
+                        .entry(withdrawn.token_contract.clone())
+                        .or_default()
+                        .withdrawn += withdrawn.amount.0;
+                }
+            }
+            // no token amounts
+            Event::ScheduleChanged(_)
+            | Event::ClaimFrozen(_)
+            | Event::ClaimUnfrozen(_)
+            | Event::ContractPaused(_)
+            | Event::ContractUnpaused(_)
+            | Event::RoleGranted(_)
+            | Event::RoleRevoked(_)
+            | Event::OwnerChanged(_)
+            | Event::OperatorChanged(_)
+            | Event::AirdropManagerChanged(_)
+            | Event::PlatformFeeChanged(_)
+            | Event::DenylistAdded(_)
+            | Event::DenylistRemoved(_)
+            | Event::AllowlistAdded(_)
+            | Event::AllowlistRemoved(_) => {}
+        }
+    }
+
+    fn airdrop(&mut self, airdrop_index: u16) -> &mut AirdropLedger {
+        self.airdrops.entry(airdrop_index).or_default()
+    }
+
+    fn set_status(&mut self, airdrop_indexes: impl Iterator<Item = u16>, status: &str) {
+        for airdrop_index in airdrop_indexes {
+            self.airdrop(airdrop_index).status = status.into();
+        }
+    }
+
+    // applies f to the user totals, and the difference to the airdrop totals
+    fn change(&mut self, account_id: &str, airdrop_index: u16, f: impl FnOnce(&mut Totals)) {
+        let user = self
+            .users
+            .entry((account_id.to_string(), airdrop_index))
+            .or_default();
+        let before = *user;
+        f(user);
+        let delta = Totals {
+            assigned: user.assigned - before.assigned,
+            claimed: user.claimed - before.claimed,
+            rolled_back: user.rolled_back - before.rolled_back,
+            revoked: user.revoked - before.revoked,
+        };
+        self.airdrop(airdrop_index).totals.add(&delta);
+    }
+}
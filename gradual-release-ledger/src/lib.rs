@@ -0,0 +1,10 @@
+//! Rebuilds the accounting of the gradual release claim contract from its NEP-297 events.
+//!
+//! Read transaction results (`tx` RPC method) with `outcome::read_transactions`,
+//! collect the contract events with `outcome::collect_events`, build a `ledger::Ledger`
+//! and export it with `csv_export`.
+
+pub mod csv_export;
+pub mod event;
+pub mod ledger;
+pub mod outcome;
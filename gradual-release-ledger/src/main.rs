@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use gradual_release_ledger::{csv_export, ledger::Ledger, outcome};
+
+const USAGE: &str =
+    "usage: gradual-release-ledger <transactions.json> [--contract <account_id>] [--out-dir <dir>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut input = None;
+    let mut contract_id = None;
+    let mut out_dir = PathBuf::from(".");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--contract" => contract_id = Some(args.next().context(USAGE)?),
+            "--out-dir" => out_dir = PathBuf::from(args.next().context(USAGE)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if input.is_none() && !arg.starts_with("--") => input = Some(arg),
+            _ => bail!("unexpected argument {}\n{}", arg, USAGE),
+        }
+    }
+    let input = input.context(USAGE)?;
+
+    let json =
+        std::fs::read_to_string(&input).with_context(|| format!("can not read {}", input))?;
+    let transactions = outcome::read_transactions(&json)?;
+    let records = outcome::collect_events(&transactions, contract_id.as_deref())?;
+    let ledger = Ledger::from_events(&records);
+
+    std::fs::create_dir_all(&out_dir)?;
+    let airdrops_path = out_dir.join("airdrops.csv");
+    let users_path = out_dir.join("users.csv");
+    let platform_fees_path = out_dir.join("platform_fees.csv");
+    csv_export::write_airdrops_csv(&ledger, File::create(&airdrops_path)?)?;
+    csv_export::write_users_csv(&ledger, File::create(&users_path)?)?;
+    csv_export::write_platform_fees_csv(&ledger, File::create(&platform_fees_path)?)?;
+    println!(
+        "{} transactions, {} events: {} airdrops to {}, {} user claims to {}, {} tokens to {}",
+        transactions.len(),
+        records.len(),
+        ledger.airdrops.len(),
+        airdrops_path.display(),
+        ledger.users.len(),
+        users_path.display(),
+        ledger.platform_fees.len(),
+        platform_fees_path.display()
+    );
+    Ok(())
+}
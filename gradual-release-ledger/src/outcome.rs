@@ -0,0 +1,80 @@
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::event::{parse_event_log, Event};
+
+/// a transaction result as returned by the `tx` RPC method, only the fields read here
+#[derive(Deserialize, Debug)]
+pub struct TransactionResult {
+    pub transaction: Transaction,
+    pub receipts_outcome: Vec<ReceiptOutcome>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Transaction {
+    pub hash: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReceiptOutcome {
+    pub id: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Outcome {
+    pub executor_id: String,
+    pub logs: Vec<String>,
+    // {"SuccessValue": ..}, {"SuccessReceiptId": ..} or {"Failure": ..}
+    pub status: serde_json::Value,
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        self.status.get("SuccessValue").is_some() || self.status.get("SuccessReceiptId").is_some()
+    }
+}
+
+/// an event and where it was logged
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub tx_hash: String,
+    pub receipt_id: String,
+    pub event: Event,
+}
+
+/// reads a JSON array of transaction results
+pub fn read_transactions(json: &str) -> anyhow::Result<Vec<TransactionResult>> {
+    serde_json::from_str(json).context("expected a JSON array of transaction results")
+}
+
+/// the contract events, in order. A failed receipt changed nothing, so its events are skipped.
+/// With `contract_id`, only the events logged by that account are read
+pub fn collect_events(
+    transactions: &[TransactionResult],
+    contract_id: Option<&str>,
+) -> anyhow::Result<Vec<EventRecord>> {
+    let mut records = vec![];
+    for transaction in transactions {
+        for receipt in &transaction.receipts_outcome {
+            let outcome = &receipt.outcome;
+            if !outcome.is_success()
+                || contract_id.is_some_and(|contract_id| outcome.executor_id != contract_id)
+            {
+                continue;
+            }
+            for log in &outcome.logs {
+                let event =
+                    parse_event_log(log).with_context(|| format!("in receipt {}", receipt.id))?;
+                if let Some(event) = event {
+                    records.push(EventRecord {
+                        tx_hash: transaction.transaction.hash.clone(),
+                        receipt_id: receipt.id.clone(),
+                        event,
+                    });
+                }
+            }
+        }
+    }
+    Ok(records)
+}
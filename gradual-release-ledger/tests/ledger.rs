@@ -0,0 +1,162 @@
+use gradual_release_ledger::{
+    csv_export,
+    event::{parse_event_log, Amount, ClaimData, Event},
+    ledger::Ledger,
+    outcome,
+};
+
+const CONTRACT_ID: &str = "gradual_release_claim_contract";
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+// hand-written in the shape test_utils::record writes, with a failed receipt and a rollback
+// the integration test does not produce. scripts/record-ledger-fixtures.sh records a sandbox run
+fn synthetic_run_ledger() -> Ledger {
+    let transactions = outcome::read_transactions(&fixture("synthetic_run.json")).unwrap();
+    let records = outcome::collect_events(&transactions, Some(CONTRACT_ID)).unwrap();
+    Ledger::from_events(&records)
+}
+
+#[test]
+fn exports_the_synthetic_run() {
+    let ledger = synthetic_run_ledger();
+    let mut airdrops_csv = vec![];
+    csv_export::write_airdrops_csv(&ledger, &mut airdrops_csv).unwrap();
+    assert_eq!(
+        String::from_utf8(airdrops_csv).unwrap(),
+        fixture("synthetic_run_airdrops.csv")
+    );
+    let mut users_csv = vec![];
+    csv_export::write_users_csv(&ledger, &mut users_csv).unwrap();
+    assert_eq!(
+        String::from_utf8(users_csv).unwrap(),
+        fixture("synthetic_run_users.csv")
+    );
+}
+
+#[test]
+fn skips_failed_receipts_and_other_contracts() {
+    let transactions = outcome::read_transactions(&fixture("synthetic_run.json")).unwrap();
+    let records = outcome::collect_events(&transactions, Some(CONTRACT_ID)).unwrap();
+    // the claims_added of the failed add_claims is not read
+    let added: Vec<&Event> = records
+        .iter()
+        .map(|record| &record.event)
+        .filter(|event| matches!(event, Event::ClaimsAdded(_)))
+        .collect();
+    assert_eq!(added.len(), 2);
+    assert!(!synthetic_run_ledger()
+        .users
+        .contains_key(&("late".to_string(), 0)));
+    // the tokens log nep141 events, another standard
+    let all_accounts = outcome::collect_events(&transactions, None).unwrap();
+    assert_eq!(all_accounts, records);
+    assert!(outcome::collect_events(&transactions, Some("token-air-0"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn parses_event_logs() {
+    let log = r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"tokens_claimed","data":[{"airdrop_index":3,"account_id":"zero","amount":"340282366920938463463374607431768211455"}]}"#;
+    assert_eq!(
+        parse_event_log(log).unwrap(),
+        Some(Event::TokensClaimed(vec![ClaimData {
+            airdrop_index: 3,
+            account_id: "zero".into(),
+            amount: Amount(u128::MAX),
+        }]))
+    );
+    assert_eq!(parse_event_log("zero claimed 10 AIR-0").unwrap(), None);
+    // a later minor version can add events
+    let log = r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.1.0","event":"airdrop_renamed","data":[{"airdrop_index":3}]}"#;
+    assert_eq!(parse_event_log(log).unwrap(), None);
+    // a new major version changes the data
+    let log = r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"2.0.0","event":"tokens_claimed","data":[]}"#;
+    assert!(parse_event_log(log).is_err());
+    let log = r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"tokens_claimed","data":[{"airdrop_index":3}]}"#;
+    assert!(parse_event_log(log).is_err());
+}
+
+#[test]
+fn update_claim_raises_or_revokes() {
+    let mut ledger = Ledger::default();
+    for log in [
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claims_added","data":[{"airdrop_index":0,"total_amount":"10","claims":[["zero","10"]]}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claim_updated","data":[{"airdrop_index":0,"account_id":"zero","old_amount":"10","new_amount":"25"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claim_updated","data":[{"airdrop_index":0,"account_id":"zero","old_amount":"25","new_amount":"0"}]}"#,
+    ] {
+        ledger.apply(&parse_event_log(log).unwrap().unwrap());
+    }
+    let totals = ledger.users[&("zero".to_string(), 0)];
+    assert_eq!(
+        (totals.assigned, totals.revoked, totals.outstanding()),
+        (25, 25, 0)
+    );
+    assert_eq!(ledger.airdrops[&0].totals, totals);
+}
+
+#[test]
+fn purge_revokes_the_unclaimed_tokens() {
+    let mut ledger = Ledger::default();
+    for log in [
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claims_added","data":[{"airdrop_index":0,"total_amount":"30","claims":[["zero","10"],["one","20"]]}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"tokens_claimed","data":[{"airdrop_index":0,"account_id":"zero","amount":"10"},{"airdrop_index":0,"account_id":"one","amount":"5"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"airdrop_closed","data":[{"airdrop_index":0}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claims_removed","data":[{"airdrop_index":0,"account_id":"zero","unclaimed_amount":"0"},{"airdrop_index":0,"account_id":"one","unclaimed_amount":"15"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"airdrop_purged","data":[{"airdrop_index":0}]}"#,
+    ] {
+        ledger.apply(&parse_event_log(log).unwrap().unwrap());
+    }
+    let zero = ledger.users[&("zero".to_string(), 0)];
+    assert_eq!((zero.claimed, zero.revoked, zero.outstanding()), (10, 0, 0));
+    let one = ledger.users[&("one".to_string(), 0)];
+    assert_eq!((one.claimed, one.revoked, one.outstanding()), (5, 15, 0));
+    let airdrop = &ledger.airdrops[&0];
+    assert_eq!(airdrop.status, "purged");
+    assert_eq!((airdrop.totals.assigned, airdrop.totals.revoked), (30, 15));
+}
+
+#[test]
+fn reconciles_sponsor_deposits_fees_and_refunds() {
+    let mut ledger = Ledger::default();
+    for log in [
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"airdrop_registered","data":[{"airdrop_index":0,"title":"Sponsored","token_contract":"token-air-0","manager_id":"sponsor","sponsor_id":"sponsor","start_ms":"0","end_ms":"10"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"sponsor_deposited","data":[{"airdrop_index":0,"sponsor_id":"sponsor","token_contract":"token-air-0","amount":"99","platform_fee":"1"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claims_added","data":[{"airdrop_index":0,"total_amount":"30","claims":[["zero","10"],["one","20"]]}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"sponsor_refunded","data":[{"airdrop_index":0,"account_id":"sponsor","amount":"69"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"tokens_claimed","data":[{"airdrop_index":0,"account_id":"zero","amount":"10"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"claims_removed","data":[{"airdrop_index":0,"account_id":"one","unclaimed_amount":"20"}]}"#,
+        r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"platform_fees_withdrawn","data":[{"token_contract":"token-air-0","receiver_id":"treasury","amount":"1"}]}"#,
+    ] {
+        ledger.apply(&parse_event_log(log).unwrap().unwrap());
+    }
+    // the unclaimed tokens of the purged claim are owed to the sponsor
+    let airdrop = &ledger.airdrops[&0];
+    assert_eq!(
+        (
+            airdrop.funds.deposited,
+            airdrop.funds.platform_fee,
+            airdrop.funds.refunded
+        ),
+        (99, 1, 69)
+    );
+    assert_eq!(airdrop.unallocated(), Some(20));
+    ledger.apply(
+        &parse_event_log(r#"EVENT_JSON:{"standard":"gradual_release_claim","version":"1.0.0","event":"sponsor_refunded","data":[{"airdrop_index":0,"account_id":"sponsor","amount":"20"}]}"#)
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(ledger.airdrops[&0].unallocated(), Some(0));
+    let fees = ledger.platform_fees["token-air-0"];
+    assert_eq!((fees.collected, fees.withdrawn, fees.balance()), (1, 1, 0));
+
+    let mut platform_fees_csv = vec![];
+    csv_export::write_platform_fees_csv(&ledger, &mut platform_fees_csv).unwrap();
+    assert_eq!(
+        String::from_utf8(platform_fees_csv).unwrap(),
+        "token_contract,collected,withdrawn,balance\ntoken-air-0,1,1,0\n"
+    );
+}
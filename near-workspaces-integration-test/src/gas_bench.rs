@@ -0,0 +1,148 @@
+use futures::future::join_all;
+use near_gas::NearGas;
+use near_sdk::json_types::{U128, U64};
+use near_workspaces::{network::Sandbox, types::NearToken, Account, AccountId, Contract, Worker};
+use std::str::FromStr;
+
+use crate::nep141_test_utils::*;
+use crate::test_utils::*;
+use crate::{create_account, create_gradual_release_contract};
+
+const BENCH_AIRDROPS: u16 = 100;
+const REGISTERED_AIRDROPS: u16 = 500;
+
+// claim gas for users with claims in 1, 10 and 100 airdrops, then with 500 registered airdrops
+// claims are stored per (account, airdrop) and airdrops are loaded one by one,
+// so the gas must grow neither with the user's airdrops nor with the registered ones
+pub async fn claim_gas_per_user_airdrops(worker: &Worker<Sandbox>) -> anyhow::Result<()> {
+    println!("gas bench: claim with 1, 10 and {} airdrops", BENCH_AIRDROPS);
+    let owner = create_account(worker, "bench-owner").await;
+    let users = vec![
+        (create_account(worker, "bench-1").await, 1),
+        (create_account(worker, "bench-10").await, 10),
+        (create_account(worker, "bench-100").await, BENCH_AIRDROPS),
+    ];
+    // claims in airdrop 0 after all the airdrops are registered
+    let late_user = create_account(worker, "bench-late").await;
+    let contract =
+        create_gradual_release_contract(worker, "bench-gradual-release", &owner, &owner).await;
+    let token_id = AccountId::from_str("token-bench").unwrap();
+    create_nep141_token(
+        worker,
+        &owner,
+        &token_id,
+        &"BENCH".to_string(),
+        6,
+        1_000_000 * 1_000_000,
+    )
+    .await;
+    storage_deposit(contract.as_account(), &token_id).await;
+    for (user, _) in users.iter() {
+        storage_deposit(user, &token_id).await;
+    }
+    storage_deposit(&late_user, &token_id).await;
+    ft_transfer(&token_id, &owner, contract.as_account(), 1_000 * 1_000_000).await?;
+
+    let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+    for airdrop_index in 0..BENCH_AIRDROPS {
+        register_bench_airdrop(&owner, &contract, &token_id, airdrop_index, now_ms).await;
+        let mut data: Vec<(String, String)> = users
+            .iter()
+            .filter(|(_, airdrops)| airdrop_index < *airdrops)
+            .map(|(user, _)| (user.id().to_string(), "1".to_string()))
+            .collect();
+        if airdrop_index == 0 {
+            data.push((late_user.id().to_string(), "1".to_string()));
+        }
+        exec(
+            owner
+                .call(contract.id(), "add_claims")
+                .args_json(serde_json::json!({
+                    "airdrop_index": airdrop_index,
+                    "total_amount": U128(data.len() as u128 * 1_000_000),
+                    "data": data,
+                }))
+                .gas(NearGas::from_tgas(50))
+                .deposit(NearToken::from_millinear(100)),
+        )
+        .await;
+    }
+    exec(
+        owner
+            .call(contract.id(), "confirm_funding")
+            .args_json(serde_json::json!({ "airdrop_index": 0 }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    exec(
+        owner
+            .call(contract.id(), "enable_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": 0 }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+
+    let mut gas_burnt = vec![];
+    for (user, airdrops) in users.iter() {
+        let gas = claim_gas(user, &contract).await?;
+        println!("claim gas with {} airdrops: {}", airdrops, gas);
+        gas_burnt.push(gas);
+    }
+    // allow 10% for the longer account ids
+    assert!(gas_burnt[2] < gas_burnt[0] * 11 / 10);
+
+    println!("gas bench: claim with {} registered airdrops", REGISTERED_AIRDROPS);
+    for first_index in (BENCH_AIRDROPS..REGISTERED_AIRDROPS).step_by(50) {
+        join_all((first_index..first_index + 50).map(|airdrop_index| {
+            register_bench_airdrop(&owner, &contract, &token_id, airdrop_index, now_ms)
+        }))
+        .await;
+    }
+    let info: serde_json::Value = contract.view("get_contract_info").await?.json()?;
+    assert_eq!(info["airdrop_count"], REGISTERED_AIRDROPS);
+    let late_claim_gas = claim_gas(&late_user, &contract).await?;
+    println!(
+        "claim gas with {} registered airdrops: {}",
+        REGISTERED_AIRDROPS, late_claim_gas
+    );
+    assert!(late_claim_gas < gas_burnt[0] * 11 / 10);
+    Ok(())
+}
+
+async fn register_bench_airdrop(
+    owner: &Account,
+    contract: &Contract,
+    token_id: &AccountId,
+    airdrop_index: u16,
+    now_ms: u64,
+) {
+    // started an hour ago, so there is something to claim now
+    exec(
+        owner
+            .call(contract.id(), "register_airdrop")
+            .args_json(serde_json::json!({
+                "title": format!("Bench airdrop {}", airdrop_index),
+                "token_contract": token_id,
+                "start_timestamp_ms": U64(now_ms - 3_600_000),
+                "end_timestamp_ms": U64(now_ms + 3_600_000),
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100)),
+    )
+    .await;
+}
+
+// gas burnt by the claim call itself, without the token transfer
+async fn claim_gas(user: &Account, contract: &Contract) -> anyhow::Result<u64> {
+    let res = user
+        .call(contract.id(), "claim")
+        .args_json(serde_json::json!({ "airdrop_index": 0 }))
+        .gas(NearGas::from_tgas(200))
+        .transact()
+        .await?;
+    let gas_burnt = res.receipt_outcomes()[0].gas_burnt;
+    check(res);
+    Ok(gas_burnt.as_gas())
+}
@@ -11,13 +11,17 @@ use near_workspaces::{
 };
 use std::{collections::HashMap, str::FromStr};
 
+mod gas_bench;
 mod nep141_test_utils;
 mod test_utils;
+mod upgrade_test;
 
 use nep141_test_utils::*;
 use test_utils::*;
 
 const GRADUAL_RELEASE_CONTRACT_FILEPATH: &str = "res/gradual_release_claim_contract.wasm";
+// short enough to wait for it in the sandbox
+const TIMELOCK_DELAY_MS: u64 = 10_000;
 
 pub const E6: u128 = 1_000_000;
 pub const E24: u128 = 1_000_000_000_000_000_000_000_000;
@@ -55,6 +59,7 @@ async fn create_gradual_release_contract(
             .args_json(serde_json::json!({
                 "owner_id": owner.id(),
                 "operator_id": operator.id(),
+                "timelock_delay_ms": U64(TIMELOCK_DELAY_MS),
             })),
     )
     .await;
@@ -175,7 +180,7 @@ async fn main() -> anyhow::Result<()> {
                 .call(gradual_release_contract.id(), "register_airdrop")
                 .args_json(args)
                 .gas(NearGas::from_tgas(50))
-                .deposit(NearToken::from_yoctonear(1))
+                .deposit(NearToken::from_millinear(100))
                 .transact()
                 .await?,
         );
@@ -239,12 +244,81 @@ async fn main() -> anyhow::Result<()> {
                     "data": claim_list,
                 }))
                 .gas(NearGas::from_tgas(50))
-                .deposit(NearToken::from_yoctonear(1))
+                .deposit(NearToken::from_millinear(100))
                 .transact()
                 .await?,
         );
     }
 
+    // -------------
+    // fix a typo in the claims before enabling: add a wrong row, update it and remove it
+    // -------------
+    let info = &tokens[0];
+    let typo_account = "tw0".to_string();
+    let typo_amount = info.amount_from_string_dec(&"10".to_string());
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+                "total_amount": U128(typo_amount),
+                "data": [(typo_account.clone(), "10")],
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100)),
+    )
+    .await;
+    // only the airdrop manager or a ClaimUploader
+    expect_error(
+        users[0]
+            .call(gradual_release_contract.id(), "update_claim")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+                "account_id": typo_account,
+                "new_amount": "0.5",
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+        &format!("is not the manager of airdrop {}", info.airdrop_index),
+    )
+    .await;
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "update_claim")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+                "account_id": typo_account,
+                "new_amount": "20.5",
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "remove_claim")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+                "account_id": typo_account,
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    let total_in_claims_per_token = gradual_release_contract
+        .view("get_total_in_claims_per_token")
+        .args_json(serde_json::json!({
+            "token_contract": info.contract_account_id,
+        }))
+        .await?
+        .json::<U128>()?;
+    let expected_total: u128 = claims_map
+        .iter()
+        .filter(|((_, index), _)| *index == info.airdrop_index)
+        .map(|(_, amount)| amount)
+        .sum();
+    assert_eq!(total_in_claims_per_token.0, expected_total);
+
     // -------------
     // expect error when try to claim, airdrop is not enabled
     // -------------
@@ -262,10 +336,51 @@ async fn main() -> anyhow::Result<()> {
         .await;
     }
 
+    // -------------
+    // an airdrop must be funded before it is enabled
+    // -------------
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "enable_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": tokens[0].airdrop_index }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+        &format!(
+            "Airdrop {} is not funded yet, call confirm_funding first",
+            tokens[0].airdrop_index
+        ),
+    )
+    .await;
+    let confirmed = join_all(tokens.iter().map(|info| {
+        operator
+            .call(gradual_release_contract.id(), "confirm_funding")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+    })).await;
+    for res in confirmed {
+        let res = res?;
+        assert!(res.is_success(), "confirm_funding failed: {:#?}", res);
+    }
+    for info in tokens.iter() {
+        let statuses: Vec<String> = gradual_release_contract
+            .view("get_airdrop_status_history")
+            .args_json(serde_json::json!({ "airdrop_index": info.airdrop_index }))
+            .await?
+            .json::<Vec<serde_json::Value>>()?
+            .iter()
+            .map(|change| change["status"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(statuses, vec!["Draft", "Funded"]);
+    }
+
     // -------------
     // enable the airdrops
     // -------------
-    join_all(tokens.iter().map(|info| {
+    let enabled = join_all(tokens.iter().map(|info| {
         operator
             .call(gradual_release_contract.id(), "enable_airdrop")
             .args_json(serde_json::json!({
@@ -275,6 +390,10 @@ async fn main() -> anyhow::Result<()> {
             .deposit(NearToken::from_yoctonear(1))
             .transact()
     })).await;
+    for res in enabled {
+        let res = res?;
+        assert!(res.is_success(), "enable_airdrop failed: {:#?}", res);
+    }
 
     // -------------
     // expect error when try to claim, schedule is not started
@@ -290,9 +409,9 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // -------------------
-    // change schedule to start 1 sec ago
+    // the airdrops are live, so schedule changes are queued behind the timelock
     // -------------------
-    join_all(tokens.iter().map(|info| {
+    let change_schedule_tx = |info: &TokenInfo| {
         operator
             .call(gradual_release_contract.id(), "change_schedule")
             .args_json(serde_json::json!({
@@ -301,8 +420,172 @@ async fn main() -> anyhow::Result<()> {
                 "end_timestamp_ms": U64(current_timestamp_ms),
             }))
             .gas(NearGas::from_tgas(50))
+            // the proposer pays the storage of the queued action
+            .deposit(NearToken::from_millinear(10))
+    };
+    let action_id: Option<u64> = check_get_value(change_schedule_tx(&tokens[0]).transact().await?);
+    let pending_actions: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_pending_actions")
+        .args_json(serde_json::json!({ "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert_eq!(pending_actions.len(), 1);
+    assert_ne!(pending_actions[0]["storage_deposit"], "0");
+    // one pending action per kind and airdrop
+    expect_error(
+        change_schedule_tx(&tokens[0]),
+        &"of the same kind is already pending".to_string(),
+    )
+    .await;
+    expect_error(
+        users[0]
+            .call(gradual_release_contract.id(), "execute_action")
+            .args_json(serde_json::json!({ "action_id": action_id.unwrap() })),
+        &"can not be executed before".to_string(),
+    )
+    .await;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "cancel_action")
+            .args_json(serde_json::json!({ "action_id": action_id.unwrap() })),
+    )
+    .await;
+
+    // -------------------
+    // the delay change is queued behind the current delay
+    // -------------------
+    let action_id: u64 = check_get_value(
+        owner
+            .call(gradual_release_contract.id(), "set_timelock_delay_ms")
+            .args_json(serde_json::json!({ "timelock_delay_ms": U64(0) }))
+            .deposit(NearToken::from_millinear(10))
             .transact()
-    })).await;
+            .await?,
+    );
+    let timelock_delay_ms: U64 = gradual_release_contract
+        .view("get_timelock_delay_ms")
+        .await?
+        .json()?;
+    assert_eq!(timelock_delay_ms.0, TIMELOCK_DELAY_MS);
+    tokio::time::sleep(std::time::Duration::from_millis(TIMELOCK_DELAY_MS)).await;
+    exec(
+        users[0]
+            .call(gradual_release_contract.id(), "execute_action")
+            .args_json(serde_json::json!({ "action_id": action_id })),
+    )
+    .await;
+
+    // -------------------
+    // without delay: change schedule to start 1 sec ago
+    // -------------------
+    for info in tokens.iter() {
+        let action_id: Option<u64> = check_get_value(change_schedule_tx(info).transact().await?);
+        exec(
+            users[0]
+                .call(gradual_release_contract.id(), "execute_action")
+                .args_json(serde_json::json!({ "action_id": action_id.unwrap() })),
+        )
+        .await;
+    }
+    // pushing the end out would lock again what users already unlocked
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "change_schedule")
+            .args_json(serde_json::json!({
+                "airdrop_index": tokens[0].airdrop_index,
+                "start_timestamp_ms": U64(current_timestamp_ms - 1000),
+                "end_timestamp_ms": U64(current_timestamp_ms + 365 * 24 * 3600 * 1000),
+            }))
+            .gas(NearGas::from_tgas(50)),
+        &"releases less than the current one".to_string(),
+    )
+    .await;
+    // only the owner can force it, even if it is not the airdrop manager
+    let end_schedule_tx = |account: &Account, end_timestamp_ms: u64, force: bool| {
+        account
+            .call(gradual_release_contract.id(), "change_schedule")
+            .args_json(serde_json::json!({
+                "airdrop_index": tokens[0].airdrop_index,
+                "start_timestamp_ms": U64(current_timestamp_ms - 1000),
+                "end_timestamp_ms": U64(end_timestamp_ms),
+                "force": force,
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(10))
+    };
+    expect_error(
+        end_schedule_tx(&operator, current_timestamp_ms + 365 * 24 * 3600 * 1000, true),
+        &format!("{} does not have the Owner role", operator.id()),
+    )
+    .await;
+    for (account, end_timestamp_ms, force) in [
+        (&owner, current_timestamp_ms + 365 * 24 * 3600 * 1000, true),
+        // back to the previous schedule, releases more so it needs no force
+        (&operator, current_timestamp_ms, false),
+    ] {
+        let action_id: Option<u64> = check_get_value(
+            end_schedule_tx(account, end_timestamp_ms, force)
+                .transact()
+                .await?,
+        );
+        exec(
+            users[0]
+                .call(gradual_release_contract.id(), "execute_action")
+                .args_json(serde_json::json!({ "action_id": action_id.unwrap() })),
+        )
+        .await;
+        let airdrops: Vec<serde_json::Value> = gradual_release_contract
+            .view("get_airdrops_including_not_enabled")
+            .await?
+            .json()?;
+        assert_eq!(
+            airdrops[tokens[0].airdrop_index as usize]["release_schedule_end_ms"],
+            end_timestamp_ms.to_string()
+        );
+    }
+
+    // -------------------
+    // top-up claims of an enabled airdrop
+    // -------------------
+    let info = &tokens[0];
+    // more than the contract holds: the claims are not added and the storage deposit is refunded
+    let prev_operator_balance = operator.view_account().await?.balance;
+    let res = operator
+        .call(gradual_release_contract.id(), "add_claims")
+        .args_json(serde_json::json!({
+            "airdrop_index": info.airdrop_index,
+            "total_amount": U128(info.amount_from_string_dec(&"300000".to_string())),
+            "data": [(users[2].id().to_string(), "300000")],
+        }))
+        .gas(NearGas::from_tgas(100))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    record(&res);
+    assert!(res.logs().iter().any(|log| log.contains("contract_balance")));
+    assert!(res.logs().iter().any(|log| log.contains("Claims not added, refunding")));
+    // only the gas was spent
+    assert!(
+        operator.view_account().await?.balance
+            > prev_operator_balance.saturating_sub(NearToken::from_millinear(10))
+    );
+    // funded top-up for an existing claim
+    let top_up_amount = info.amount_from_string_dec(&"100.5".to_string());
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": info.airdrop_index,
+                "total_amount": U128(top_up_amount),
+                "data": [(users[2].id().to_string(), "100.5")],
+            }))
+            .gas(NearGas::from_tgas(100))
+            .deposit(NearToken::from_millinear(100)),
+    )
+    .await;
+    *claims_map
+        .get_mut(&(users[2].id().to_string(), info.airdrop_index))
+        .unwrap() += top_up_amount;
 
     // -------------------
     // check the airdrops
@@ -314,12 +597,14 @@ async fn main() -> anyhow::Result<()> {
         pub enabled: bool,
         pub title: String,
         pub token_contract: AccountId,
+        pub manager_id: AccountId,
         pub token_symbol: String,
         pub token_decimals: u8,
         pub release_schedule_start_ms: U64,
         pub release_schedule_end_ms: U64,
         pub total_distributed: U128,
         pub total_claimed: U128,
+        pub storage_deposit: U128,
     }
 
     let view_result_details = gradual_release_contract
@@ -332,6 +617,10 @@ async fn main() -> anyhow::Result<()> {
     println!("{:?}", airdrops);
     for airdrop in airdrops.iter() {
         assert_eq!(airdrop.enabled, true);
+        // the operator registered them, so it is the manager
+        assert_eq!(&airdrop.manager_id, operator.id());
+        // and paid for their storage
+        assert!(airdrop.storage_deposit.0 > 0);
         // no claims yet
         assert_eq!(airdrop.total_claimed.0, 0);
 
@@ -352,6 +641,26 @@ async fn main() -> anyhow::Result<()> {
     }
 
 
+    // the lifecycle of each airdrop is recorded
+    #[derive(Debug, serde::Deserialize)]
+    pub struct StatusChangeJSON {
+        pub status: String,
+        pub timestamp_ms: U64,
+    }
+    for airdrop in airdrops.iter() {
+        let history = gradual_release_contract
+            .view("get_airdrop_status_history")
+            .args_json(serde_json::json!({
+                "airdrop_index": airdrop.airdrop_index,
+            }))
+            .await?
+            .json::<Vec<StatusChangeJSON>>()?;
+        println!("{:?}", history);
+        let statuses: Vec<&str> = history.iter().map(|c| c.status.as_str()).collect();
+        assert_eq!(statuses, vec!["Draft", "Funded", "Active"]);
+        assert!(history[0].timestamp_ms.0 <= history[2].timestamp_ms.0);
+    }
+
     // -------------
     // expect error when try to claim, user "zero" is not registered (storage deposit)
     // "The account zero is not registered"
@@ -374,6 +683,134 @@ async fn main() -> anyhow::Result<()> {
         )
     ).await;
 
+    // -------------
+    // emergency pause by a Pauser: claims are blocked, views still work
+    // -------------
+    let pauser = &users[2];
+    expect_error(
+        pauser
+            .call(gradual_release_contract.id(), "pause")
+            .deposit(NearToken::from_yoctonear(1)),
+        &"does not have the Pauser role".to_string(),
+    )
+    .await;
+    let res = owner
+        .call(gradual_release_contract.id(), "grant_role")
+        .args_json(serde_json::json!({
+            "account_id": pauser.id(),
+            "role": "Pauser",
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "role_granted"),
+        vec![serde_json::json!({ "account_id": pauser.id(), "role": "Pauser" })]
+    );
+    check(res);
+    let pausers: Vec<AccountId> = gradual_release_contract
+        .view("get_role_holders")
+        .args_json(serde_json::json!({ "role": "Pauser" }))
+        .await?
+        .json()?;
+    assert_eq!(pausers, vec![owner.id().clone(), pauser.id().clone()]);
+    let res = pauser
+        .call(gradual_release_contract.id(), "pause")
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "contract_paused"),
+        vec![serde_json::json!({ "account_id": pauser.id() })]
+    );
+    check(res);
+    let contract_info: serde_json::Value =
+        gradual_release_contract.view("get_contract_info").await?.json()?;
+    assert_eq!(contract_info["paused"], true);
+    expect_error(
+        users[0]
+            .call(gradual_release_contract.id(), "claim")
+            .args_json(serde_json::json!({
+                "airdrop_index": 0,
+            }))
+            .gas(NearGas::from_tgas(150)),
+        &"The contract is paused".to_string(),
+    )
+    .await;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "unpause")
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "revoke_role")
+            .args_json(serde_json::json!({
+                "account_id": pauser.id(),
+                "role": "Pauser",
+            }))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+
+    // -------------
+    // compliance hold on a single user: claiming fails with the reason until unfrozen
+    // -------------
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "freeze_user_claim")
+            .args_json(serde_json::json!({
+                "account_id": users[1].id(),
+                "airdrop_index": 0,
+                "reason": "x".repeat(257),
+            }))
+            .deposit(NearToken::from_yoctonear(1)),
+        &"The reason can have at most 256 bytes".to_string(),
+    )
+    .await;
+    let res = operator
+        .call(gradual_release_contract.id(), "freeze_user_claim")
+        .args_json(serde_json::json!({
+            "account_id": users[1].id(),
+            "airdrop_index": 0,
+            "reason": "legal hold",
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "claim_frozen"),
+        vec![serde_json::json!({
+            "airdrop_index": 0,
+            "account_id": users[1].id(),
+            "reason": "legal hold",
+        })]
+    );
+    check(res);
+    let user_claims: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_user_claims")
+        .args_json(serde_json::json!({ "account_id": users[1].id() }))
+        .await?
+        .json()?;
+    assert_eq!(user_claims[0]["frozen"], true);
+    assert_eq!(user_claims[0]["frozen_reason"], "legal hold");
+    expect_error(
+        users[1]
+            .call(gradual_release_contract.id(), "claim")
+            .args_json(serde_json::json!({ "airdrop_index": 0 }))
+            .gas(NearGas::from_tgas(150)),
+        &"is frozen: legal hold".to_string(),
+    )
+    .await;
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "unfreeze_user_claim")
+            .args_json(serde_json::json!({ "account_id": users[1].id() }))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+
     // -------------
     // re-try the claims
     // -------------
@@ -390,12 +827,17 @@ async fn main() -> anyhow::Result<()> {
                     "airdrop_index": info.airdrop_index,
                 }))
                 .gas(NearGas::from_tgas(150));
-            exec(claim_tx).await;
+            let res = claim_tx.transact().await?;
+            let claimed = get_events(&res, "tokens_claimed");
+            check(res);
 
             let expected_amount = *claims_map
                 .get(&(user.id().to_string(), info.airdrop_index))
                 .unwrap();
             assert!(expected_amount > 0);
+            assert_eq!(claimed.len(), 1);
+            assert_eq!(claimed[0]["account_id"], user.id().to_string());
+            assert_eq!(claimed[0]["amount"], expected_amount.to_string());
 
             let new_balance = ft_balance(token_contract, user.id()).await?;
             // println!("new_balance: {} {}", new_balance, info.symbol);
@@ -430,5 +872,567 @@ async fn main() -> anyhow::Result<()> {
         assert_eq!(total_in_claims_per_token.0, 0);
     }
 
+    // -------------------
+    // cancel an airdrop registered by mistake, and get the tokens back
+    // -------------------
+    let info = &tokens[0];
+    let wrong_airdrop_index: u16 = check_get_value(
+        operator
+            .call(gradual_release_contract.id(), "register_airdrop")
+            .args_json(serde_json::json!({
+                "title": "Wrong airdrop",
+                "token_contract": info.contract_account_id,
+                "start_timestamp_ms": U64(current_timestamp_ms),
+                "end_timestamp_ms": U64(current_timestamp_ms + 30_000),
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100))
+            .transact()
+            .await?,
+    );
+    let wrong_amount = info.amount_from_string_dec(&"50".to_string());
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": wrong_airdrop_index,
+                "total_amount": U128(wrong_amount),
+                "data": [(users[1].id().to_string(), "50")],
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100)),
+    )
+    .await;
+    let token_contract = &nep141_contracts[info.airdrop_index as usize];
+    // the operator is not registered with the token, the refund transfer fails
+    let res = operator
+        .call(gradual_release_contract.id(), "cancel_airdrop")
+        .args_json(serde_json::json!({
+            "airdrop_index": wrong_airdrop_index,
+            "refund_to": operator.id(),
+        }))
+        .gas(NearGas::from_tgas(200))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert!(get_events(&res, "cancel_refunded").is_empty());
+    let airdrops: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_airdrops_including_not_enabled")
+        .await?
+        .json()?;
+    assert_eq!(airdrops[wrong_airdrop_index as usize]["cancelled"], true);
+    assert_eq!(
+        airdrops[wrong_airdrop_index as usize]["failed_cancel_refund"],
+        wrong_amount.to_string()
+    );
+    // the tokens stay committed, the refund is sent again once the operator is registered
+    storage_deposit(&operator, &info.contract_account_id).await;
+    let res = operator
+        .call(gradual_release_contract.id(), "retry_cancel_refund")
+        .args_json(serde_json::json!({ "airdrop_index": wrong_airdrop_index }))
+        .gas(NearGas::from_tgas(100))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "cancel_refunded"),
+        vec![serde_json::json!({
+            "airdrop_index": wrong_airdrop_index,
+            "account_id": operator.id(),
+            "amount": U128(wrong_amount),
+        })]
+    );
+    check(res);
+    assert_eq!(ft_balance(token_contract, operator.id()).await?, wrong_amount);
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "retry_cancel_refund")
+            .args_json(serde_json::json!({ "airdrop_index": wrong_airdrop_index }))
+            .deposit(NearToken::from_yoctonear(1)),
+        &format!("Airdrop {} has no failed cancel refund", wrong_airdrop_index),
+    )
+    .await;
+    let res = operator
+        .call(gradual_release_contract.id(), "remove_cancelled_claims")
+        .args_json(serde_json::json!({
+            "airdrop_index": wrong_airdrop_index,
+            "from_index": 0,
+            "limit": 10,
+        }))
+        .gas(NearGas::from_tgas(50))
+        .transact()
+        .await?;
+    // the cancel already revoked the claims, nothing more is unclaimed
+    let removed = get_events(&res, "claims_removed");
+    assert!(!removed.is_empty());
+    assert!(removed
+        .iter()
+        .all(|row| row["airdrop_index"] == wrong_airdrop_index && row["unclaimed_amount"] == "0"));
+    let next_index: u32 = check_get_value(res);
+    let contract_info: serde_json::Value =
+        gradual_release_contract.view("get_contract_info").await?.json()?;
+    assert_eq!(contract_info["user_count"], next_index);
+    let total_in_claims_per_token = gradual_release_contract
+        .view("get_total_in_claims_per_token")
+        .args_json(serde_json::json!({
+            "token_contract": info.contract_account_id,
+        }))
+        .await?
+        .json::<U128>()?;
+    assert_eq!(total_in_claims_per_token.0, 0);
+
+    // -------------------
+    // sponsored airdrop: the owner funds it with ft_transfer_call, 1% platform fee,
+    // the unassigned tokens go back to the sponsor when it is closed
+    // -------------------
+    let res = owner
+        .call(gradual_release_contract.id(), "set_platform_fee_bp")
+        .args_json(serde_json::json!({ "platform_fee_bp": 100 }))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "platform_fee_changed"),
+        vec![serde_json::json!({ "old_fee_bp": 0, "new_fee_bp": 100 })]
+    );
+    check(res);
+    let sponsored_airdrop_index: Option<u16> = check_get_value(
+        owner
+            .call(gradual_release_contract.id(), "register_sponsored_airdrop")
+            .args_json(serde_json::json!({
+                "title": "Sponsored airdrop",
+                "token_contract": info.contract_account_id,
+                "start_timestamp_ms": U64(current_timestamp_ms),
+                "end_timestamp_ms": U64(current_timestamp_ms + 30_000),
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100))
+            .transact()
+            .await?,
+    );
+    let sponsored_airdrop_index = sponsored_airdrop_index.unwrap();
+    let deposit_amount = info.amount_from_string_dec(&"100".to_string());
+    let res = owner
+        .call(&info.contract_account_id, "ft_transfer_call")
+        .args_json(serde_json::json!({
+            "receiver_id": gradual_release_contract.id(),
+            "amount": U128(deposit_amount),
+            "msg": sponsored_airdrop_index.to_string(),
+        }))
+        .gas(NearGas::from_tgas(100))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    let platform_fee = deposit_amount / 100;
+    assert_eq!(
+        get_events(&res, "sponsor_deposited"),
+        vec![serde_json::json!({
+            "airdrop_index": sponsored_airdrop_index,
+            "sponsor_id": owner.id(),
+            "token_contract": info.contract_account_id,
+            "amount": U128(deposit_amount - platform_fee),
+            "platform_fee": U128(platform_fee),
+        })]
+    );
+    check(res);
+    let platform_fees = gradual_release_contract
+        .view("get_platform_fees")
+        .args_json(serde_json::json!({
+            "token_contract": info.contract_account_id,
+        }))
+        .await?
+        .json::<U128>()?;
+    assert_eq!(platform_fees.0, platform_fee);
+    let sponsored_claim = info.amount_from_string_dec(&"50".to_string());
+    let prev_storage_deposit: U128 = gradual_release_contract
+        .view("get_storage_deposit")
+        .args_json(serde_json::json!({ "account_id": owner.id() }))
+        .await?
+        .json()?;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": sponsored_airdrop_index,
+                "total_amount": U128(sponsored_claim),
+                "data": [(users[1].id().to_string(), "50")],
+            }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_millinear(100)),
+    )
+    .await;
+    // the claim records its payer, who gets the storage back when it is removed
+    let storage_deposit: U128 = gradual_release_contract
+        .view("get_storage_deposit")
+        .args_json(serde_json::json!({ "account_id": owner.id() }))
+        .await?
+        .json()?;
+    assert!(storage_deposit.0 > prev_storage_deposit.0);
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "confirm_funding")
+            .args_json(serde_json::json!({ "airdrop_index": sponsored_airdrop_index }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "enable_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": sponsored_airdrop_index }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    // compliance hold: pause the airdrop and its vesting clock, then resume
+    let action_id: u64 = check_get_value(
+        owner
+            .call(gradual_release_contract.id(), "disable_airdrop")
+            .args_json(serde_json::json!({
+                "airdrop_index": sponsored_airdrop_index,
+                "freeze_accrual": true,
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?,
+    );
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "execute_action")
+            .args_json(serde_json::json!({ "action_id": action_id })),
+    )
+    .await;
+    let airdrops: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_airdrops_including_not_enabled")
+        .await?
+        .json()?;
+    assert!(!airdrops[sponsored_airdrop_index as usize]["frozen_since_ms"].is_null());
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "enable_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": sponsored_airdrop_index }))
+            .gas(NearGas::from_tgas(50))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    let airdrops: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_airdrops_including_not_enabled")
+        .await?
+        .json()?;
+    assert!(airdrops[sponsored_airdrop_index as usize]["frozen_since_ms"].is_null());
+    // queued while its release has not ended, the timelock delay is 0 by now
+    let action_id: Option<u64> = check_get_value(
+        owner
+            .call(gradual_release_contract.id(), "archive_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": sponsored_airdrop_index }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?,
+    );
+    if let Some(action_id) = action_id {
+        exec(
+            users[0]
+                .call(gradual_release_contract.id(), "execute_action")
+                .args_json(serde_json::json!({ "action_id": action_id })),
+        )
+        .await;
+    }
+    let prev_owner_balance = ft_balance(token_contract, owner.id()).await?;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "close_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": sponsored_airdrop_index }))
+            .gas(NearGas::from_tgas(200)),
+    )
+    .await;
+    assert_eq!(
+        ft_balance(token_contract, owner.id()).await?,
+        prev_owner_balance + deposit_amount - platform_fee - sponsored_claim
+    );
+    // closed after launch, not cancelled: its claims can not be removed as cancelled
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "remove_cancelled_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": sponsored_airdrop_index,
+                "from_index": 0,
+                "limit": 10,
+            }))
+            .gas(NearGas::from_tgas(50)),
+        &format!("Airdrop {} was not cancelled", sponsored_airdrop_index),
+    )
+    .await;
+
+    // -------------------
+    // denylisted accounts can not receive claims
+    // -------------------
+    exec(
+        operator
+            .call(gradual_release_contract.id(), "add_to_denylist")
+            .args_json(serde_json::json!({ "accounts": ["exploiter"] }))
+            .deposit(NearToken::from_millinear(10)),
+    )
+    .await;
+    let denylist: Vec<AccountId> = gradual_release_contract
+        .view("get_denylist")
+        .args_json(serde_json::json!({ "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert_eq!(denylist, vec![AccountId::from_str("exploiter").unwrap()]);
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": 1,
+                "total_amount": U128(tokens[1].amount_from_string_dec(&"1".to_string())),
+                "data": [("exploiter", "1")],
+            }))
+            .gas(NearGas::from_tgas(100))
+            .deposit(NearToken::from_millinear(100)),
+        &"exploiter is denylisted".to_string(),
+    )
+    .await;
+    // the allowlist can not block the users of a live airdrop
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "set_allowlist_required")
+            .args_json(serde_json::json!({ "airdrop_index": 1, "allowlist_required": true })),
+        &"the allowlist can only be required before launch".to_string(),
+    )
+    .await;
+
+    // -------------------
+    // malformed account ids are reported by validate_claims and fail the whole batch
+    // -------------------
+    let data = [
+        (users[0].id().to_string(), "1".to_string()),
+        (" Two.near".to_string(), "1".to_string()),
+        ("exploiter".to_string(), "1".to_string()),
+    ];
+    let invalid_rows: Vec<serde_json::Value> = gradual_release_contract
+        .view("validate_claims")
+        .args_json(serde_json::json!({ "airdrop_index": 1, "data": data }))
+        .await?
+        .json()?;
+    assert_eq!(invalid_rows.len(), 2);
+    assert_eq!(invalid_rows[0]["row"], 1);
+    assert_eq!(invalid_rows[1]["error"], "exploiter is denylisted");
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "add_claims")
+            .args_json(serde_json::json!({
+                "airdrop_index": 1,
+                "total_amount": U128(tokens[1].amount_from_string_dec(&"3".to_string())),
+                "data": data,
+            }))
+            .gas(NearGas::from_tgas(100))
+            .deposit(NearToken::from_millinear(100)),
+        &"data[1]: invalid account id".to_string(),
+    )
+    .await;
+
+    // -------------------
+    // only the manager (or an AirdropAdmin) can manage an airdrop
+    // -------------------
+    expect_error(
+        users[1]
+            .call(gradual_release_contract.id(), "archive_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": 0 })),
+        &"is not the manager of airdrop 0".to_string(),
+    )
+    .await;
+
+    // -------------------
+    // two-step operator handover: only the nominee can accept, the owner can cancel
+    // -------------------
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "propose_operator")
+            .args_json(serde_json::json!({ "operator_id": users[1].id() }))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    let nominations: serde_json::Value = gradual_release_contract
+        .view("get_pending_nominations")
+        .await?
+        .json()?;
+    assert_eq!(nominations["operator_id"], users[1].id().to_string());
+    expect_error(
+        users[2]
+            .call(gradual_release_contract.id(), "accept_operator")
+            .deposit(NearToken::from_yoctonear(1)),
+        &"Only the proposed operator can accept".to_string(),
+    )
+    .await;
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "cancel_operator_proposal")
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    let nominations: serde_json::Value = gradual_release_contract
+        .view("get_pending_nominations")
+        .await?
+        .json()?;
+    assert!(nominations["operator_id"].is_null());
+
+    // -------------------
+    // cleanup of fully claimed claims, the freed storage goes back to the storage payer (the operator)
+    // -------------------
+    exec(
+        owner
+            .call(gradual_release_contract.id(), "set_storage_treasury")
+            .args_json(serde_json::json!({ "storage_treasury_id": owner.id() })),
+    )
+    .await;
+    let prev_operator_balance = operator.view_account().await?.balance;
+    let pruned: u32 = check_get_value(
+        users[0]
+            .call(gradual_release_contract.id(), "prune_my_claims")
+            .gas(NearGas::from_tgas(100))
+            .transact()
+            .await?,
+    );
+    assert!(pruned > 0);
+    let user_claims: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_user_claims_including_inactive")
+        .args_json(serde_json::json!({ "account_id": users[0].id() }))
+        .await?
+        .json()?;
+    assert!(user_claims.is_empty());
+    assert!(operator.view_account().await?.balance > prev_operator_balance);
+    let mut from_index = 0;
+    let mut removed_claims = 0;
+    let user_count = loop {
+        let progress: serde_json::Value = check_get_value(
+            users[2]
+                .call(gradual_release_contract.id(), "cleanup_fully_claimed")
+                .args_json(serde_json::json!({ "from_index": from_index, "limit": 2 }))
+                .gas(NearGas::from_tgas(100))
+                .transact()
+                .await?,
+        );
+        removed_claims += progress["removed_claims"].as_u64().unwrap();
+        from_index = progress["next_index"].as_u64().unwrap();
+        let user_count = progress["user_count"].as_u64().unwrap();
+        if from_index >= user_count {
+            break user_count;
+        }
+    };
+    assert!(removed_claims > 0);
+    // users[1] keeps the unclaimed sponsored claim
+    assert!(user_count > 0);
+
+    // -------------------
+    // purge the closed airdrops: the claims are removed in batches, then a tombstone keeps the final totals
+    // the unclaimed sponsored claim of users[1] is refunded to the sponsor before the tombstone
+    // -------------------
+    expect_error(
+        users[1]
+            .call(gradual_release_contract.id(), "purge_airdrop")
+            .args_json(serde_json::json!({
+                "airdrop_index": sponsored_airdrop_index,
+                "from_index": 0,
+                "limit": 1,
+            })),
+        &format!("is not the manager of airdrop {}", sponsored_airdrop_index),
+    )
+    .await;
+    let prev_owner_balance = ft_balance(token_contract, owner.id()).await?;
+    let mut from_index = 0;
+    let mut sponsor_refunds = vec![];
+    loop {
+        let res = owner
+            .call(gradual_release_contract.id(), "purge_airdrop")
+            .args_json(serde_json::json!({
+                "airdrop_index": sponsored_airdrop_index,
+                "from_index": from_index,
+                "limit": 1,
+            }))
+            .gas(NearGas::from_tgas(150))
+            .transact()
+            .await?;
+        sponsor_refunds.extend(get_events(&res, "sponsor_refunded"));
+        let progress: serde_json::Value = check_get_value(res);
+        if progress["purged"].as_bool().unwrap() {
+            break;
+        }
+        // with no claims left, the call refunded the sponsor and the next one purges
+        if progress["claims_left"].as_u64().unwrap() > 0 {
+            from_index = progress["next_index"].as_u64().unwrap();
+            assert!(from_index < progress["user_count"].as_u64().unwrap());
+        }
+    }
+    assert_eq!(
+        ft_balance(token_contract, owner.id()).await?,
+        prev_owner_balance + sponsored_claim
+    );
+    assert_eq!(
+        sponsor_refunds,
+        vec![serde_json::json!({
+            "airdrop_index": sponsored_airdrop_index,
+            "account_id": owner.id(),
+            "amount": U128(sponsored_claim),
+        })]
+    );
+    // the cancelled airdrop has no claims left, it is purged in one call
+    let res = operator
+        .call(gradual_release_contract.id(), "purge_airdrop")
+        .args_json(serde_json::json!({
+            "airdrop_index": wrong_airdrop_index,
+            "from_index": 0,
+            "limit": 10,
+        }))
+        .gas(NearGas::from_tgas(100))
+        .transact()
+        .await?;
+    assert_eq!(
+        get_events(&res, "airdrop_purged"),
+        vec![serde_json::json!({ "airdrop_index": wrong_airdrop_index })]
+    );
+    let progress: serde_json::Value = check_get_value(res);
+    assert_eq!(progress["purged"], true);
+    expect_error(
+        operator
+            .call(gradual_release_contract.id(), "purge_airdrop")
+            .args_json(serde_json::json!({
+                "airdrop_index": wrong_airdrop_index,
+                "from_index": 0,
+                "limit": 10,
+            })),
+        &"The airdrop was purged".to_string(),
+    )
+    .await;
+    let purged: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_purged_airdrops")
+        .await?
+        .json()?;
+    assert_eq!(purged.len(), 2);
+    assert_eq!(purged[1]["airdrop_index"], sponsored_airdrop_index);
+    assert_eq!(purged[1]["total_distributed"], sponsored_claim.to_string());
+    // the indexes do not change, the purged airdrops are no longer listed
+    let airdrops: Vec<serde_json::Value> = gradual_release_contract
+        .view("get_airdrops_including_not_enabled")
+        .await?
+        .json()?;
+    assert!(airdrops
+        .iter()
+        .all(|airdrop| airdrop["airdrop_index"] != sponsored_airdrop_index));
+    assert_eq!(airdrops[0]["airdrop_index"], 0);
+    let contract_info: serde_json::Value =
+        gradual_release_contract.view("get_contract_info").await?.json()?;
+    assert_eq!(contract_info["airdrop_count"], sponsored_airdrop_index + 1);
+
+    // the totals match the claims, the purge removed the unclaimed sponsored claim from them
+    let audit = run_audit(&gradual_release_contract).await?;
+    assert_eq!(audit["mismatches"], serde_json::json!([]));
+    assert!(!audit["tokens"].as_array().unwrap().is_empty());
+
+    if let Ok(path) = std::env::var("EVENT_FIXTURE_PATH") {
+        write_recorded_transactions(&path)?;
+    }
+
+    gas_bench::claim_gas_per_user_airdrops(&worker).await?;
+    upgrade_test::v2_state_survives_upgrade(&worker).await?;
+
     Ok(())
 }
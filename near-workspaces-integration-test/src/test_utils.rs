@@ -1,6 +1,10 @@
 use core::panic;
+use std::sync::Mutex;
 
-use near_workspaces::{operations::CallTransaction, result::ExecutionFinalResult};
+use near_workspaces::{operations::CallTransaction, result::ExecutionFinalResult, Contract};
+
+// transaction results checked here, in the `tx` RPC format read by gradual-release-ledger
+static RECORDED: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
 
 // execute a call transaction and panic if it fails
 pub async fn exec(call_tx: CallTransaction) {
@@ -9,6 +13,7 @@ pub async fn exec(call_tx: CallTransaction) {
 }
 
 pub fn check(res: ExecutionFinalResult) {
+    record(&res);
     if res.failures().len() > 0 {
         println!("error: {:#?}", res);
         panic!("execution failed");
@@ -19,6 +24,7 @@ pub fn check_get_value<T>(res: ExecutionFinalResult) -> T
 where
     T: serde::de::DeserializeOwned,
 {
+    record(&res);
     if res.failures().len() > 0 {
         println!("error: {:#?}", res);
         panic!("execution failed");
@@ -27,8 +33,80 @@ where
     result
 }
 
+// the data of every NEP-297 event with this name, from all the receipts
+pub fn get_events(res: &ExecutionFinalResult, event: &str) -> Vec<serde_json::Value> {
+    res.logs()
+        .iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .map(|json| serde_json::from_str::<serde_json::Value>(json).unwrap())
+        .filter(|event_log| {
+            event_log["standard"] == "gradual_release_claim" && event_log["event"] == event
+        })
+        .flat_map(|event_log| event_log["data"].as_array().unwrap().clone())
+        .collect()
+}
+
+fn record(res: &ExecutionFinalResult) {
+    let receipts_outcome: Vec<serde_json::Value> = res
+        .receipt_outcomes()
+        .iter()
+        .map(|outcome| {
+            // the id of a receipt outcome is in transaction_hash
+            serde_json::json!({
+                "id": outcome.transaction_hash.to_string(),
+                "outcome": {
+                    "executor_id": outcome.executor_id,
+                    "logs": outcome.logs,
+                    "status": if outcome.is_success() {
+                        serde_json::json!({ "SuccessValue": "" })
+                    } else {
+                        serde_json::json!({ "Failure": {} })
+                    },
+                },
+            })
+        })
+        .collect();
+    RECORDED.lock().unwrap().push(serde_json::json!({
+        "transaction": { "hash": res.outcome().transaction_hash.to_string() },
+        "receipts_outcome": receipts_outcome,
+    }));
+}
+
+// writes the recorded transactions, to regenerate the gradual-release-ledger fixtures
+pub fn write_recorded_transactions(path: &str) -> anyhow::Result<()> {
+    let recorded = RECORDED.lock().unwrap();
+    std::fs::write(path, serde_json::to_string_pretty(&*recorded)?)?;
+    println!("{} transactions written to {}", recorded.len(), path);
+    Ok(())
+}
+
+// runs the audit view one user per page, returns the final page
+pub async fn run_audit(contract: &Contract) -> anyhow::Result<serde_json::Value> {
+    let mut from_index = 0;
+    let mut sums = serde_json::Value::Null;
+    loop {
+        let page: serde_json::Value = contract
+            .view("audit")
+            .args_json(serde_json::json!({
+                "from_index": from_index,
+                "limit": 1,
+                "sums": sums,
+            }))
+            .await?
+            .json()?;
+        if page["done"].as_bool().unwrap() {
+            println!("audit: {}", page);
+            return Ok(page);
+        }
+        assert!(page["mismatches"].as_array().unwrap().is_empty());
+        from_index = page["next_index"].as_u64().unwrap();
+        sums = page["sums"].clone();
+    }
+}
+
 pub async fn expect_error(call_tx: CallTransaction, err_message: &String) {
     let res = call_tx.transact().await.unwrap();
+    record(&res);
     if res.failures().len() == 0 {
         panic!("res.failures().len() > 0 -- got 0 failures! {}", err_message);
     }
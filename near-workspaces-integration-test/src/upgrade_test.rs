@@ -0,0 +1,359 @@
+use near_gas::NearGas;
+use near_sdk::json_types::{U128, U64};
+use near_workspaces::{
+    network::Sandbox,
+    types::{KeyType, NearToken, SecretKey},
+    Account, AccountId, Contract, Worker,
+};
+use serde_json::Value;
+use std::str::FromStr;
+
+use crate::nep141_test_utils::*;
+use crate::test_utils::*;
+use crate::{create_account, DEV_ACCOUNT_SEED, GRADUAL_RELEASE_CONTRACT_FILEPATH};
+
+// built by scripts/build-v2.0.0.sh
+const V2_CONTRACT_FILEPATH: &str = "res/gradual_release_claim_contract_v2.0.0.wasm";
+
+// v2.0.0 fields replaced by a new field
+const RENAMED_FIELDS: [&str; 1] = ["status_code"];
+
+// deploy v2.0.0, fill it, upgrade to the current code and check the views did not change
+pub async fn v2_state_survives_upgrade(worker: &Worker<Sandbox>) -> anyhow::Result<()> {
+    println!("upgrade test: v2.0.0 to current");
+    let owner = create_account(worker, "upgrade-owner").await;
+    let operator = create_account(worker, "upgrade-operator").await;
+    let users = vec![
+        create_account(worker, "upgrade-0").await,
+        create_account(worker, "upgrade-1").await,
+        create_account(worker, "upgrade-2").await,
+    ];
+    let wasm_code = std::fs::read(V2_CONTRACT_FILEPATH).unwrap();
+    let contract = worker
+        .create_tla_and_deploy(
+            AccountId::from_str("upgrade-gradual-release").unwrap(),
+            SecretKey::from_seed(KeyType::ED25519, DEV_ACCOUNT_SEED),
+            &wasm_code,
+        )
+        .await?
+        .unwrap();
+    exec(contract.call("new").args_json(serde_json::json!({
+        "owner_id": owner.id(),
+        "operator_id": operator.id(),
+    })))
+    .await;
+    let token_id = AccountId::from_str("token-upgrade").unwrap();
+    create_nep141_token(
+        worker,
+        &owner,
+        &token_id,
+        &"UPG".to_string(),
+        6,
+        1_000_000 * 1_000_000,
+    )
+    .await;
+    storage_deposit(contract.as_account(), &token_id).await;
+    for user in users.iter() {
+        storage_deposit(user, &token_id).await;
+    }
+    ft_transfer(&token_id, &owner, contract.as_account(), 1_000 * 1_000_000).await?;
+
+    // 0: ended, claimed by user 0. 1: future schedule. 2: never enabled. 3: archived
+    let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+    let schedules = [
+        (now_ms - 7_200_000, now_ms - 3_600_000),
+        (now_ms + 86_400_000, now_ms + 2 * 86_400_000),
+        (now_ms - 3_600_000, now_ms + 3_600_000),
+        (now_ms - 7_200_000, now_ms - 3_600_000),
+    ];
+    for (airdrop_index, (start_ms, end_ms)) in schedules.iter().enumerate() {
+        exec(
+            operator
+                .call(contract.id(), "register_airdrop")
+                .args_json(serde_json::json!({
+                    "title": format!("v2 airdrop {}", airdrop_index),
+                    "token_contract": token_id,
+                    "start_timestamp_ms": U64(*start_ms),
+                    "end_timestamp_ms": U64(*end_ms),
+                }))
+                .gas(NearGas::from_tgas(50))
+                .deposit(NearToken::from_yoctonear(1)),
+        )
+        .await;
+        let data: Vec<(String, String)> = users
+            .iter()
+            .skip(airdrop_index % 2)
+            .map(|user| (user.id().to_string(), format!("{}.5", airdrop_index + 1)))
+            .collect();
+        let total_amount: u128 = data
+            .iter()
+            .map(|(_, amount)| parse_token_amount(amount, 6))
+            .sum();
+        exec(
+            operator
+                .call(contract.id(), "add_claims")
+                .args_json(serde_json::json!({
+                    "airdrop_index": airdrop_index,
+                    "total_amount": U128(total_amount),
+                    "data": data,
+                }))
+                .gas(NearGas::from_tgas(50))
+                .deposit(NearToken::from_yoctonear(1)),
+        )
+        .await;
+        if airdrop_index != 2 {
+            exec(
+                operator
+                    .call(contract.id(), "enable_airdrop")
+                    .args_json(serde_json::json!({ "airdrop_index": airdrop_index }))
+                    .gas(NearGas::from_tgas(50))
+                    .deposit(NearToken::from_yoctonear(1)),
+            )
+            .await;
+        }
+    }
+    exec(
+        users[0]
+            .call(contract.id(), "claim")
+            .args_json(serde_json::json!({ "airdrop_index": 0 }))
+            .gas(NearGas::from_tgas(200)),
+    )
+    .await;
+    exec(
+        operator
+            .call(contract.id(), "archive_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": 3 })),
+    )
+    .await;
+
+    let before = all_views(&contract, &users, &token_id).await?;
+
+    // upgrade
+    let wasm_code = std::fs::read(GRADUAL_RELEASE_CONTRACT_FILEPATH).unwrap();
+    contract.as_account().deploy(&wasm_code).await?.into_result()?;
+    exec(contract.call("migrate").gas(NearGas::from_tgas(100))).await;
+
+    let info: Value = contract.view("get_contract_info").await?.json()?;
+    assert_eq!(info["state_version"], 3);
+    let after = all_views(&contract, &users, &token_id).await?;
+    assert_same_fields(&before, &after, "views");
+    // the legacy claims add up to the migrated totals
+    let audit = run_audit(&contract).await?;
+    assert_eq!(audit["mismatches"], Value::Array(vec![]));
+
+    // a legacy user claims, moving to the new layout
+    exec(
+        users[1]
+            .call(contract.id(), "claim")
+            .args_json(serde_json::json!({ "airdrop_index": 0 }))
+            .gas(NearGas::from_tgas(200)),
+    )
+    .await;
+    let claims: Value = contract
+        .view("get_user_claims_including_inactive")
+        .args_json(serde_json::json!({ "account_id": users[1].id() }))
+        .await?
+        .json()?;
+    assert_eq!(claims[0]["claimed_tokens"], claims[0]["assigned_tokens"]);
+
+    // move the rest, the views stay the same
+    let before = all_views(&contract, &users, &token_id).await?;
+    loop {
+        let remaining: u64 = check_get_value(
+            operator
+                .call(contract.id(), "migrate_claims")
+                .args_json(serde_json::json!({ "limit": 1 }))
+                .gas(NearGas::from_tgas(100))
+                .transact()
+                .await?,
+        );
+        if remaining == 0 {
+            break;
+        }
+    }
+    let after = all_views(&contract, &users, &token_id).await?;
+    assert_same_fields(&before, &after, "views after migrate_claims");
+
+    // the owner redeploys the same version with upgrade, the wasm is the raw argument
+    expect_error(
+        operator
+            .call(contract.id(), "upgrade")
+            .args(wasm_code.clone())
+            .gas(NearGas::from_tgas(300))
+            .deposit(NearToken::from_yoctonear(1)),
+        &"does not have the Owner role".to_string(),
+    )
+    .await;
+    exec(
+        owner
+            .call(contract.id(), "upgrade")
+            .args(wasm_code.clone())
+            .gas(NearGas::from_tgas(300))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    let after_redeploy = all_views(&contract, &users, &token_id).await?;
+    assert_same_fields(&after, &after_redeploy, "views after upgrade");
+
+    // staged: the owner stages the code paying for its storage, then deploys it by hash
+    let staging_deposit = NearToken::from_yoctonear(wasm_code.len() as u128 * 10u128.pow(19))
+        .saturating_add(NearToken::from_millinear(10));
+    expect_error(
+        operator
+            .call(contract.id(), "stage_code")
+            .args(wasm_code.clone())
+            .gas(NearGas::from_tgas(300))
+            .deposit(staging_deposit),
+        &"does not have the Owner role".to_string(),
+    )
+    .await;
+    exec(
+        owner
+            .call(contract.id(), "stage_code")
+            .args(wasm_code.clone())
+            .gas(NearGas::from_tgas(300))
+            .deposit(staging_deposit),
+    )
+    .await;
+    // staging again replaces the code and refunds the first deposit, the owner only pays the gas
+    let gas_margin = NearToken::from_millinear(100).as_yoctonear();
+    let owner_balance = owner.view_account().await?.balance.as_yoctonear();
+    exec(
+        owner
+            .call(contract.id(), "stage_code")
+            .args(wasm_code.clone())
+            .gas(NearGas::from_tgas(300))
+            .deposit(staging_deposit),
+    )
+    .await;
+    assert!(owner.view_account().await?.balance.as_yoctonear() + gas_margin > owner_balance);
+    let code_hash: Option<String> = contract.view("get_staged_code_hash").await?.json()?;
+    let code_hash = code_hash.unwrap();
+    expect_error(
+        owner
+            .call(contract.id(), "deploy_staged_code")
+            .args_json(serde_json::json!({ "code_hash": "11111111111111111111111111111111" }))
+            .gas(NearGas::from_tgas(300))
+            .deposit(NearToken::from_yoctonear(1)),
+        &"The staged code does not match code_hash".to_string(),
+    )
+    .await;
+    let owner_balance = owner.view_account().await?.balance.as_yoctonear();
+    exec(
+        owner
+            .call(contract.id(), "deploy_staged_code")
+            .args_json(serde_json::json!({ "code_hash": code_hash }))
+            .gas(NearGas::from_tgas(300))
+            .deposit(NearToken::from_yoctonear(1)),
+    )
+    .await;
+    // migrate removes the staged code and refunds its storage deposit to the owner
+    let code_hash: Option<String> = contract.view("get_staged_code_hash").await?.json()?;
+    assert!(code_hash.is_none());
+    assert!(
+        owner.view_account().await?.balance.as_yoctonear() + gas_margin
+            > owner_balance + wasm_code.len() as u128 * 10u128.pow(19)
+    );
+    let after_staged = all_views(&contract, &users, &token_id).await?;
+    assert_same_fields(&after, &after_staged, "views after deploy_staged_code");
+
+    // the migrated history of the archived airdrop 3 has no Active entry, it still is not a cancelled airdrop:
+    // purging it takes the unclaimed tokens of users 1 and 2 out of the token total
+    let total_before: U128 = contract
+        .view("get_total_in_claims_per_token")
+        .args_json(serde_json::json!({ "token_contract": token_id }))
+        .await?
+        .json()?;
+    exec(
+        operator
+            .call(contract.id(), "close_airdrop")
+            .args_json(serde_json::json!({ "airdrop_index": 3 })),
+    )
+    .await;
+    let res = operator
+        .call(contract.id(), "purge_airdrop")
+        .args_json(serde_json::json!({ "airdrop_index": 3, "from_index": 0, "limit": 10 }))
+        .gas(NearGas::from_tgas(100))
+        .transact()
+        .await?;
+    let removed = get_events(&res, "claims_removed");
+    assert_eq!(removed.len(), 2);
+    assert!(removed.iter().all(|row| row["unclaimed_amount"] == "4500000"));
+    let progress: Value = check_get_value(res);
+    assert_eq!(progress["purged"], true);
+    let total_after: U128 = contract
+        .view("get_total_in_claims_per_token")
+        .args_json(serde_json::json!({ "token_contract": token_id }))
+        .await?
+        .json()?;
+    assert_eq!(total_after.0, total_before.0 - 2 * 4_500_000);
+    let audit = run_audit(&contract).await?;
+    assert_eq!(audit["mismatches"], Value::Array(vec![]));
+    Ok(())
+}
+
+// every v2.0.0 view, get_users sorted by account
+async fn all_views(
+    contract: &Contract,
+    users: &[Account],
+    token_id: &AccountId,
+) -> anyhow::Result<Value> {
+    let mut user_list: Vec<Value> = contract
+        .view("get_users")
+        .args_json(serde_json::json!({ "from_index": 0, "limit": 100 }))
+        .await?
+        .json()?;
+    user_list.sort_by_key(|user| user["account_id"].as_str().unwrap().to_string());
+    let mut user_claims = vec![];
+    for user in users {
+        for method in ["get_user_claims", "get_user_claims_including_inactive"] {
+            let claims: Value = contract
+                .view(method)
+                .args_json(serde_json::json!({ "account_id": user.id() }))
+                .await?
+                .json()?;
+            user_claims.push(claims);
+        }
+    }
+    let mut contract_info: Value = contract.view("get_contract_info").await?.json()?;
+    contract_info.as_object_mut().unwrap().remove("state_version");
+    Ok(serde_json::json!({
+        "get_contract_info": contract_info,
+        "get_airdrops": contract.view("get_airdrops").await?.json::<Value>()?,
+        "get_airdrops_including_not_enabled": contract
+            .view("get_airdrops_including_not_enabled")
+            .await?
+            .json::<Value>()?,
+        "get_total_in_claims_per_token": contract
+            .view("get_total_in_claims_per_token")
+            .args_json(serde_json::json!({ "token_contract": token_id }))
+            .await?
+            .json::<Value>()?,
+        "get_users": user_list,
+        "get_user_claims": user_claims,
+    }))
+}
+
+// every field of `before` must be in `after` with the same value, new fields are ignored
+fn assert_same_fields(before: &Value, after: &Value, path: &str) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in before {
+                if RENAMED_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                let new_value = after
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{}.{} is missing", path, key));
+                assert_same_fields(value, new_value, &format!("{}.{}", path, key));
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            assert_eq!(before.len(), after.len(), "{} length", path);
+            for (index, (value, new_value)) in before.iter().zip(after).enumerate() {
+                assert_same_fields(value, new_value, &format!("{}[{}]", path, index));
+            }
+        }
+        _ => assert_eq!(before, after, "{}", path),
+    }
+}
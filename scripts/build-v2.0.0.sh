@@ -0,0 +1,56 @@
+# build the v2.0.0 contract, the integration test deploys it and upgrades it to the current version
+# V2_REF: upstream tag or commit of the v2.0.0 source deployed on mainnet, e.g. V2_REF=<tag> bash scripts/build-v2.0.0.sh
+# the wasm must match the code deployed on V2_CONTRACT_ID, or the code hash in V2_CODE_HASH (base58, to run offline)
+set -e
+V2_WASM=res/gradual_release_claim_contract_v2.0.0.wasm
+V2_CONTRACT_ID=${V2_CONTRACT_ID:-meta-pool-airdrop-gradual-release.near}
+MAINNET_RPC=${MAINNET_RPC:-https://rpc.mainnet.near.org}
+
+# base58 sha256 of a file, as the code_hash of view_account
+code_hash() {
+    python3 - "$1" <<'EOF'
+import hashlib, sys
+digest = hashlib.sha256(open(sys.argv[1], "rb").read()).digest()
+alphabet = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+number, encoded = int.from_bytes(digest, "big"), ""
+while number:
+    number, remainder = divmod(number, 58)
+    encoded = alphabet[remainder] + encoded
+print("1" * (len(digest) - len(digest.lstrip(b"\0"))) + encoded)
+EOF
+}
+
+check_code_hash() {
+    if [ -z "$V2_CODE_HASH" ]; then
+        V2_CODE_HASH=$(curl -sf "$MAINNET_RPC" -H 'Content-Type: application/json' \
+            -d '{"jsonrpc":"2.0","id":"build-v2","method":"query","params":{"request_type":"view_account","finality":"final","account_id":"'$V2_CONTRACT_ID'"}}' \
+            | python3 -c 'import json, sys; print(json.load(sys.stdin)["result"]["code_hash"])')
+    fi
+    BUILT_HASH=$(code_hash $V2_WASM)
+    if [ "$BUILT_HASH" != "$V2_CODE_HASH" ]; then
+        echo "ERR: $V2_WASM code hash $BUILT_HASH, $V2_CONTRACT_ID runs $V2_CODE_HASH"
+        rm $V2_WASM
+        exit 1
+    fi
+    echo "$V2_WASM matches the code of $V2_CONTRACT_ID ($V2_CODE_HASH)"
+}
+
+if [ -f $V2_WASM ]; then
+    echo "$V2_WASM already built"
+    check_code_hash
+    exit 0
+fi
+if [ -z "$V2_REF" ]; then
+    echo "ERR: set V2_REF to the upstream tag or commit of v2.0.0"
+    exit 1
+fi
+export RUSTFLAGS='-C link-arg=-s'
+set -x
+rm -rf target/v2.0.0-src
+git worktree add --detach target/v2.0.0-src $V2_REF
+(cd target/v2.0.0-src && cargo build -p gradual-release-claim-contract --target wasm32-unknown-unknown --release)
+mkdir -p res
+cp target/v2.0.0-src/target/wasm32-unknown-unknown/release/gradual_release_claim_contract.wasm $V2_WASM
+git worktree remove --force target/v2.0.0-src
+set +x
+check_code_hash
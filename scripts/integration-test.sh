@@ -1,2 +1,3 @@
 bash scripts/build.sh
+bash scripts/build-v2.0.0.sh
 RUST_BACKTRACE=1 cargo run -p near-workspaces-integration-test
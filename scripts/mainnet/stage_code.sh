@@ -0,0 +1,18 @@
+#!/bin/bash
+__dir=$(dirname "$0")
+. $__dir/mainnet-set-vars.sh
+
+echo meta-vote-contract: $CONTRACT_ADDRESS
+ls -l $CONTRACT_WASM
+
+# Stage the new code, no full-access key needed.
+# The owner pays its storage, 0.00001 NEAR per byte (the excess is refunded),
+# then calls deploy_staged_code with the hash printed below, which deploys the code and calls migrate
+echo STAGE CODE
+STAGING_DEPOSIT=$(awk "BEGIN { print $(stat -c%s $CONTRACT_WASM) / 100000 + 0.01 }")
+set -ex
+NEAR_ENV=mainnet \
+    near call $CONTRACT_ADDRESS stage_code "$(base64 -w0 $CONTRACT_WASM)" --base64 \
+    --accountId $OWNER_ID --deposit $STAGING_DEPOSIT --gas $TOTAL_PREPAID_GAS
+NEAR_ENV=mainnet \
+    near view $CONTRACT_ADDRESS get_staged_code_hash
@@ -0,0 +1,9 @@
+# record the transactions of the integration test and the reports the ledger derives from them
+set -e
+FIXTURES=gradual-release-ledger/fixtures
+EVENT_FIXTURE_PATH=$PWD/$FIXTURES/sandbox_run.json bash scripts/integration-test.sh
+REPORTS=$(mktemp -d)
+cargo run -p gradual-release-ledger -- $FIXTURES/sandbox_run.json --contract gradual_release_claim_contract --out-dir $REPORTS
+cp $REPORTS/airdrops.csv $FIXTURES/sandbox_run_airdrops.csv
+cp $REPORTS/users.csv $FIXTURES/sandbox_run_users.csv
+rm -r $REPORTS
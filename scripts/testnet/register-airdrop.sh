@@ -38,7 +38,8 @@ EOA
 )
 echo "$REGISTER_ARGS"
 
-near call $CONTRACT_ADDRESS "register_airdrop" "$REGISTER_ARGS" --accountId $OPERATOR_ID --depositYocto 1
+# pays the storage of the airdrop, the excess is refunded
+near call $CONTRACT_ADDRESS "register_airdrop" "$REGISTER_ARGS" --accountId $OPERATOR_ID --deposit 0.1
 
 near call $TOKEN_ADDRESS "storage_deposit" '{"account_id":"'$CONTRACT_ADDRESS'"}' --accountId $OPERATOR_ID --deposit 0.0125
 
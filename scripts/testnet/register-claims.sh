@@ -55,7 +55,12 @@ ADD_CLAIMS_ARGS=$(cat <<EOA
 EOA
 )
 
-near call $CONTRACT_ADDRESS "add_claims" "$ADD_CLAIMS_ARGS" --accountId $OPERATOR_ID --depositYocto  1
+# pays the storage of the new claims, 0.01 NEAR per row covers it, the excess is refunded
+ROWS=$(echo "$ADD_CLAIMS_ARGS" | grep -c '^\[')
+STORAGE_DEPOSIT=$(awk "BEGIN { print $ROWS * 0.01 }")
+near call $CONTRACT_ADDRESS "add_claims" "$ADD_CLAIMS_ARGS" --accountId $OPERATOR_ID --deposit $STORAGE_DEPOSIT
+
+near call $CONTRACT_ADDRESS "confirm_funding" '{"airdrop_index":'$AIRDROP_INDEX'}' --accountId $OPERATOR_ID --depositYocto  1
 
 near call $CONTRACT_ADDRESS "enable_airdrop" '{"airdrop_index":'$AIRDROP_INDEX'}' --accountId $OPERATOR_ID --depositYocto  1
 
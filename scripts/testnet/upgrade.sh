@@ -0,0 +1,13 @@
+#!/bin/bash
+__dir=$(dirname "$0")
+. $__dir/0-testnet-set-vars.sh
+
+echo meta-vote-contract: $CONTRACT_ADDRESS
+ls -l $CONTRACT_WASM
+
+# Owner upgrade: deploys the code and calls migrate, no full-access key needed
+echo UPGRADE
+set -ex
+NEAR_ENV=testnet \
+    near call $CONTRACT_ADDRESS upgrade "$(base64 -w0 $CONTRACT_WASM)" --base64 \
+    --accountId $OWNER_ID --depositYocto 1 --gas $TOTAL_PREPAID_GAS